@@ -3,6 +3,7 @@ use std::net::IpAddr;
 use admin_client::run_admin_client;
 use clap::Parser;
 use common::defaults::{IP, PORT};
+use common::net::transport::GameTransport;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -14,13 +15,18 @@ struct Args {
     /// IP to host server on
     #[arg(short, long, default_value_t = IP)]
     ip: IpAddr,
+
+    /// Transport to accept clients on, can be given several times to listen on each of them.
+    /// One of udp, tcp or ws, WebSocket runs on the port after the game port
+    #[arg(short, long, default_value = "udp")]
+    transport: Vec<GameTransport>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
     let args = Args::parse();
 
-    run_admin_client(args.ip, args.port)?;
+    run_admin_client(args.ip, args.port, args.transport)?;
 
     Ok(())
 }
@@ -6,11 +6,12 @@ use std::{
 use common::Signal;
 use message_io::node::NodeHandler;
 use notan::{
-    egui::{self, EguiPluginSugar, ScrollArea, Ui},
+    egui::{self, EguiPluginSugar, Grid, ScrollArea, Ui},
     prelude::{App, Assets, Color, Graphics, Plugins},
     AppState,
 };
-use server::server::Server;
+use common::net::transport::GameTransport;
+use server::server::{Server, ServerConfig, SharedNetStats};
 
 #[derive(AppState, Clone)]
 pub struct Program {
@@ -18,27 +19,47 @@ pub struct Program {
 
     ip: IpAddr,
     port: u16,
+    transports: Vec<GameTransport>,
     messages: Arc<Mutex<Vec<String>>>,
+    net_stats: SharedNetStats,
 
     should_exit_on_server_closing: bool,
+
+    // Kick/ban form
+    player_name: String,
+    reason: String,
 }
 
 impl Program {
-    pub fn new(ip: IpAddr, port: u16, should_exit_on_server_closing: bool) -> Program {
+    pub fn new(
+        ip: IpAddr,
+        port: u16,
+        transports: Vec<GameTransport>,
+        should_exit_on_server_closing: bool,
+    ) -> Program {
         Program {
             ip,
             port,
+            transports,
             messages: Arc::new(Mutex::new(Vec::new())),
+            net_stats: SharedNetStats::default(),
             server_handler: None,
             should_exit_on_server_closing,
+            player_name: String::new(),
+            reason: String::new(),
         }
     }
 
     pub fn run(&mut self) -> io::Result<()> {
         let addr = SocketAddr::new(self.ip, self.port);
         println!("Starting server on {addr}");
-        let (mut server, mut logger_reciever) = Server::new(addr, true)?;
+        let config = ServerConfig {
+            transports: self.transports.clone(),
+            ..ServerConfig::default()
+        };
+        let (mut server, mut logger_reciever) = Server::new(addr, config, true)?;
         self.server_handler = Some(server.handler.clone());
+        self.net_stats = Arc::clone(&server.net_stats);
 
         tokio::spawn(async move {
             server.run();
@@ -63,9 +84,14 @@ impl Program {
         }
     }
 
+    /// Disconnects everyone with a reason and stops the server
     pub fn stop(&self) {
-        if self.is_running() {
-            self.server_handler.clone().unwrap().stop()
+        self.signal(Signal::Shutdown);
+    }
+
+    fn signal(&self, signal: Signal) {
+        if let Some(server_handler) = self.server_handler.as_ref().filter(|_| self.is_running()) {
+            server_handler.signals().send(signal);
         }
     }
 
@@ -79,16 +105,78 @@ impl Program {
             ui.add_space(10.0);
 
             if self.is_running() {
-                ui.label(format!("Server running on {}:{}", self.ip, self.port));
+                let transports: Vec<String> =
+                    self.transports.iter().map(|transport| transport.to_string()).collect();
+                ui.label(format!(
+                    "Server running on {}:{} over {}",
+                    self.ip,
+                    self.port,
+                    transports.join(", ")
+                ));
 
                 if ui.button("Stop server").clicked() {
                     self.stop();
                 }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.player_name)
+                        .on_hover_text("Player name");
+                    ui.text_edit_singleline(&mut self.reason)
+                        .on_hover_text("Reason");
+
+                    if ui.button("Kick").clicked() {
+                        self.signal(Signal::Kick {
+                            name: self.player_name.clone(),
+                            reason: self.reason.clone(),
+                        });
+                    }
+
+                    if ui.button("Ban").clicked() {
+                        self.signal(Signal::Ban {
+                            name: self.player_name.clone(),
+                            reason: self.reason.clone(),
+                        });
+                    }
+                });
             } else {
                 ui.label("Server is not currently running");
             }
         });
 
+        if self.is_running() {
+            ui.add_space(10.0);
+            let report = self.net_stats.lock().unwrap();
+
+            ui.heading("Clients:");
+            ui.add_space(5.0);
+            Grid::new("client_stats").striped(true).show(ui, |ui| {
+                for header in ["Name", "Address", "RTT", "Jitter", "Loss", "In", "Out", "Dropped"] {
+                    ui.label(header);
+                }
+                ui.end_row();
+
+                for client in report.clients.iter() {
+                    let stats = &client.stats;
+                    ui.label(&client.name);
+                    ui.label(client.addr.to_string());
+                    ui.label(stats.ping_ms().map_or_else(|| "-".to_string(), |ping| format!("{ping} ms")));
+                    ui.label(format!("{} ms", stats.jitter.as_millis()));
+                    ui.label(format!("{:.1}%", stats.loss * 100.0));
+                    ui.label(format!("{:.1} kB/s", stats.bytes_in_per_sec / 1000.0));
+                    ui.label(format!("{:.1} kB/s", stats.bytes_out_per_sec / 1000.0));
+                    ui.label(client.dropped.to_string());
+                    ui.end_row();
+                }
+            });
+
+            ui.add_space(5.0);
+            ui.label(format!(
+                "Dropped messages: {} over rate limits, {} invalid, {} from temporarily banned addresses",
+                report.dropped.rate_limited, report.dropped.invalid, report.dropped.banned
+            ));
+        }
+
         ui.heading("Messages:");
         ui.add_space(5.0);
         ScrollArea::vertical()
@@ -105,10 +193,11 @@ impl Program {
 pub fn notan_setup(
     ip: IpAddr,
     port: u16,
+    transports: Vec<GameTransport>,
     should_exit_on_server_closing: bool,
 ) -> impl Fn(&mut App, &mut Assets, &mut Graphics, &mut Plugins) -> Program {
     move |_, _, _, _| {
-        let mut p = Program::new(ip, port, should_exit_on_server_closing);
+        let mut p = Program::new(ip, port, transports.clone(), should_exit_on_server_closing);
         p.run().unwrap();
         p
     }
@@ -0,0 +1,172 @@
+use std::f32::consts::TAU;
+use std::net::{IpAddr, SocketAddr};
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+use client::client::{Client, ClientError, ClientReceiver, ClientSender};
+use common::ecs::components::InputState;
+use common::net::stats::NetStatsSummary;
+use common::net::transport::GameTransport;
+use common::net::Sequence;
+use common::defaults::TICKS_PER_SECOND;
+use common::{FromClientMessage, FromServerMessage, Tick};
+use message_io::network::RemoteAddr;
+use rand::Rng;
+use tokio::sync::mpsc::error::TryRecvError;
+
+/// How fast a circling bot turns, in radians per second
+const TURN_SPEED: f32 = 1.5;
+
+/// How a bot decides what to press
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Behaviour {
+    /// Connects and stands still, only the cost of having a player around
+    Idle,
+    /// Walks in circles and shoots once a second, the same every run
+    Circle,
+    /// Presses random keys and looks around, changing its mind every few seconds
+    Random,
+}
+
+/// A simulated player, connected like a real client but without a window
+pub struct Bot {
+    name: String,
+    client: Client,
+    receiver: ClientReceiver,
+    sender: ClientSender,
+
+    behaviour: Behaviour,
+    input: InputState,
+    input_sequence: Sequence,
+    /// Seconds until the random behaviour picks new inputs
+    next_change: f32,
+    /// Seconds since the bot joined
+    alive: f32,
+
+    /// Tick of the newest snapshot, inputs are only sent once there is one
+    snapshot_tick: Option<Tick>,
+    /// Tick of the last scoreboard, worked out from the snapshots as scoreboards don't say
+    scoreboard_tick: Option<Tick>,
+}
+
+impl Bot {
+    pub fn connect(ip: IpAddr, port: u16, transport: GameTransport, name: String, behaviour: Behaviour) -> anyhow::Result<Self> {
+        let addr = RemoteAddr::Socket(SocketAddr::new(ip, transport.port(port)?));
+        let mut client = Client::new(addr, transport)?;
+        let (receiver, sender) = client.start(&name)?;
+
+        Ok(Bot {
+            name,
+            client,
+            receiver,
+            sender,
+            behaviour,
+            input: InputState::default(),
+            input_sequence: 0,
+            next_change: 0.0,
+            alive: 0.0,
+            snapshot_tick: None,
+            scoreboard_tick: None,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn stats(&self) -> NetStatsSummary {
+        self.client.stats()
+    }
+
+    /// Newest server tick this bot has heard of.
+    /// Snapshots are skipped while nothing changes, scoreboards keep counting even then.
+    pub fn latest_tick(&self) -> Option<Tick> {
+        self.snapshot_tick.max(self.scoreboard_tick)
+    }
+
+    /// Handles everything the server sent since the last step, then sends this step's inputs.
+    /// An error means the bot isn't connected anymore.
+    pub fn step(&mut self, dt: f32) -> anyhow::Result<()> {
+        let mut ack = None;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Ok(FromServerMessage::Snapshot(snapshot))) => {
+                    ack = Some(snapshot.tick);
+                    self.snapshot_tick = self.snapshot_tick.max(Some(snapshot.tick));
+                }
+                Ok(Ok(FromServerMessage::Scoreboard(_))) => {
+                    // Scoreboards go out on every multiple of TICKS_PER_SECOND, so this one is on the next one after what we've seen
+                    let next = self.scoreboard_tick.map(|tick| tick + TICKS_PER_SECOND);
+                    let after_snapshot = self
+                        .snapshot_tick
+                        .map(|tick| tick.div_ceil(TICKS_PER_SECOND) * TICKS_PER_SECOND);
+                    self.scoreboard_tick = next.max(after_snapshot);
+                }
+                Ok(Ok(FromServerMessage::JoinResponse(Err(rejection)))) => return Err(anyhow!(rejection)),
+                Ok(Ok(FromServerMessage::Disconnect(reason))) => return Err(anyhow!(ClientError::Removed(reason))),
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => return Err(anyhow!(err)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Err(anyhow!(ClientError::Disconnected)),
+            }
+        }
+
+        // Bots don't keep any state to apply snapshots to, but acking them keeps the server sending deltas like it would to a player
+        if let Some(tick) = ack {
+            self.sender.send(FromClientMessage::AckSnapshot(tick))?;
+        }
+
+        // The server has nothing to apply inputs to before it sent us our player
+        let view_tick = match self.snapshot_tick {
+            Some(tick) => tick,
+            None => return Ok(()),
+        };
+
+        self.alive += dt;
+        let changed = self.think(dt);
+
+        // Same as the game, movement gets sent every frame and everything else only when it changes
+        if changed || self.input.is_moving() {
+            self.input_sequence += 1;
+            // Bots don't interpolate, they see the newest snapshot they got
+            self.sender.send(FromClientMessage::UpdateInputs(
+                self.input_sequence,
+                self.input,
+                view_tick,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Updates the inputs, returns true if they changed
+    fn think(&mut self, dt: f32) -> bool {
+        let before = self.input;
+
+        match self.behaviour {
+            Behaviour::Idle => {}
+            Behaviour::Circle => {
+                self.input.forward = true;
+                self.input.look_angle = (self.input.look_angle + TURN_SPEED * dt).rem_euclid(TAU);
+                // Holds the trigger for the first tenth of every second
+                self.input.shoot = self.alive.fract() < 0.1;
+            }
+            Behaviour::Random => {
+                let mut rng = rand::thread_rng();
+
+                self.next_change -= dt;
+                if self.next_change <= 0.0 {
+                    self.next_change = rng.gen_range(0.5..3.0);
+                    self.input.forward = rng.gen_bool(0.6);
+                    self.input.backward = !self.input.forward && rng.gen_bool(0.3);
+                    self.input.left = rng.gen_bool(0.3);
+                    self.input.right = !self.input.left && rng.gen_bool(0.3);
+                    self.input.shoot = rng.gen_bool(0.2);
+                }
+                self.input.look_angle = (self.input.look_angle + rng.gen_range(-TURN_SPEED..TURN_SPEED) * dt).rem_euclid(TAU);
+            }
+        }
+
+        self.input != before
+    }
+}
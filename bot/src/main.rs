@@ -0,0 +1,198 @@
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use common::defaults::{IP, MAX_PLAYERS, PORT, TICKS_PER_SECOND};
+use common::net::transport::GameTransport;
+use common::Tick;
+
+use crate::bot::{Behaviour, Bot};
+
+mod bot;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Connects simulated players to a server and reports how it holds up", long_about = None)]
+struct Args {
+    /// IP of the server to load
+    #[arg(short, long, default_value_t = IP)]
+    ip: IpAddr,
+
+    /// Port of the server to load
+    #[arg(short, long, default_value_t = PORT)]
+    port: u16,
+
+    /// Transport to connect with, one of udp, tcp or ws
+    #[arg(short, long, default_value = "udp")]
+    transport: GameTransport,
+
+    /// How many bots to connect, the server's --max-players has to allow this many
+    #[arg(short = 'n', long, default_value_t = MAX_PLAYERS)]
+    bots: usize,
+
+    /// How the bots play
+    #[arg(short, long, value_enum, default_value_t = Behaviour::Random)]
+    behaviour: Behaviour,
+
+    /// Bots are named this followed by a number
+    #[arg(long, default_value = "bot")]
+    name: String,
+
+    /// How many times a second each bot sends its inputs.
+    /// Every input moves the player by one tick, so the default is what a real client sends
+    #[arg(long, default_value_t = TICKS_PER_SECOND as u32)]
+    input_rate: u32,
+
+    /// Delay between connecting bots in milliseconds, so they don't all join on the same tick
+    #[arg(long, default_value_t = 50)]
+    join_interval: u64,
+
+    /// Seconds between reports
+    #[arg(long, default_value_t = 1)]
+    report_interval: u64,
+
+    /// Seconds to run for before disconnecting everyone, runs until Ctrl-C if not given
+    #[arg(short, long)]
+    duration: Option<u64>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let mut bots = Vec::with_capacity(args.bots);
+    let mut next_join = Instant::now();
+    let mut report = Report::new();
+
+    let started = Instant::now();
+    let step = Duration::from_secs_f64(1.0 / args.input_rate.max(1) as f64);
+    let report_interval = Duration::from_secs(args.report_interval.max(1));
+    let duration = args.duration.map(Duration::from_secs);
+
+    let mut interval = tokio::time::interval(step);
+    let mut last_step = Instant::now();
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    println!("Connecting {} bots to {}:{} over {}", args.bots, args.ip, args.port, args.transport);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = &mut ctrl_c => break,
+        }
+
+        if duration.map_or(false, |duration| started.elapsed() >= duration) {
+            break;
+        }
+
+        let spawned = report.joined + report.failed;
+        if spawned < args.bots && Instant::now() >= next_join {
+            next_join = Instant::now() + Duration::from_millis(args.join_interval);
+
+            let name = format!("{}{}", args.name, spawned + 1);
+            match Bot::connect(args.ip, args.port, args.transport, name.clone(), args.behaviour) {
+                Ok(bot) => {
+                    bots.push(bot);
+                    report.joined += 1;
+                }
+                Err(err) => {
+                    println!("{name} couldn't connect: {err}");
+                    report.failed += 1;
+                }
+            }
+        }
+
+        let dt = last_step.elapsed().as_secs_f32();
+        last_step = Instant::now();
+
+        bots.retain_mut(|bot| match bot.step(dt) {
+            Ok(()) => true,
+            Err(err) => {
+                println!("{} left: {err}", bot.name());
+                report.left += 1;
+                false
+            }
+        });
+
+        if report.started.elapsed() >= report_interval {
+            report.print(started.elapsed(), &bots);
+        }
+    }
+
+    println!("Disconnecting {} bots", bots.len());
+    // Dropping a client sends Leave, so the server doesn't have to wait for them to time out
+    drop(bots);
+    // Give the leave messages a moment to go out before the runtime shuts down
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    Ok(())
+}
+
+/// What the bots saw since the last report
+struct Report {
+    started: Instant,
+    /// Newest tick any bot had seen at the start of the report
+    first_tick: Option<Tick>,
+
+    joined: usize,
+    failed: usize,
+    left: usize,
+}
+
+impl Report {
+    fn new() -> Self {
+        Report {
+            started: Instant::now(),
+            first_tick: None,
+            joined: 0,
+            failed: 0,
+            left: 0,
+        }
+    }
+
+    /// Prints a line summing up the last interval and starts the next one
+    fn print(&mut self, uptime: Duration, bots: &[Bot]) {
+        let seconds = self.started.elapsed().as_secs_f32();
+        let latest_tick = bots.iter().filter_map(Bot::latest_tick).max();
+
+        // The ticks the bots hear of tell how fast the server runs
+        let tick_rate = match (self.first_tick, latest_tick) {
+            (Some(first), Some(latest)) => format!("{:.1}", latest.saturating_sub(first) as f32 / seconds),
+            _ => "-".to_string(),
+        };
+
+        let stats: Vec<_> = bots.iter().map(Bot::stats).collect();
+        let bytes_in: f32 = stats.iter().map(|stats| stats.bytes_in_per_sec).sum();
+        let bytes_out: f32 = stats.iter().map(|stats| stats.bytes_out_per_sec).sum();
+        let pings: Vec<u32> = stats.iter().filter_map(|stats| stats.ping_ms()).collect();
+        let ping = match pings.len() {
+            0 => "-".to_string(),
+            len => format!("{}", pings.iter().sum::<u32>() / len as u32),
+        };
+        let loss = match stats.len() {
+            0 => 0.0,
+            len => stats.iter().map(|stats| stats.loss).sum::<f32>() / len as f32,
+        };
+        let per_bot = match bots.len() {
+            0 => 0.0,
+            len => bytes_in / len as f32,
+        };
+
+        println!(
+            "[{:>4}s] {} bots ({} failed, {} left) | {} ticks/s | in {:.1} KB/s ({:.1} KB/s per bot) | out {:.1} KB/s | ping {} ms | loss {:.1}%",
+            uptime.as_secs(),
+            bots.len(),
+            self.failed,
+            self.left,
+            tick_rate,
+            bytes_in / 1000.0,
+            per_bot / 1000.0,
+            bytes_out / 1000.0,
+            ping,
+            loss * 100.0,
+        );
+
+        self.started = Instant::now();
+        self.first_tick = latest_tick;
+    }
+}
@@ -1,4 +1,7 @@
 use clap::Parser;
+use common::defaults::MASTER_SERVER;
+use common::net::simulator::SimArgs;
+use common::net::transport::GameTransport;
 use once_cell::sync::Lazy;
 
 #[derive(Parser, Debug)]
@@ -7,6 +10,23 @@ pub struct Args {
     /// If to enable debug features
     #[arg(short, long, default_value_t = false)]
     pub debug: bool,
+
+    /// How far in the past other players are rendered in milliseconds.
+    /// Higher values hide more jitter and packet loss at the cost of seeing others later
+    #[arg(long, default_value_t = 100)]
+    pub interpolation_window: u64,
+
+    /// Transport to connect to servers with, one of udp, tcp or ws
+    #[arg(short, long, default_value = "udp")]
+    pub transport: GameTransport,
+
+    /// Master server to fetch the public server list from
+    #[arg(long, default_value = MASTER_SERVER)]
+    pub master: String,
+
+    // Bad connection to simulate for everything the client sends
+    #[command(flatten)]
+    pub sim: SimArgs,
 }
 
 pub static ARGS: Lazy<Args> = Lazy::new(Args::parse);
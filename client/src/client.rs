@@ -1,15 +1,27 @@
 use core::time;
+use std::sync::{Arc, Mutex};
 use std::{error::Error, fmt::Display, net::SocketAddr};
 
 use chrono::{DateTime, Duration, Utc};
-use common::{FromClientMessage, FromServerMessage};
+use common::net::channel::ReliableReceiver;
+use common::net::fragment::{Reassembler, REASSEMBLY_TIMEOUT};
+use common::net::simulator::{NetConditions, Network};
+use common::net::stats::{NetStats, NetStatsSummary};
+use common::net::transport::GameTransport;
+use common::net::codec::{self, CodecError};
+use common::net::compression;
+use common::net::{Channel, Packet};
+use common::handshake::{JoinRejection, JoinRequest};
+use common::{ClientMessage, DisconnectReason, FromClientMessage, FromServerMessage, SessionToken};
 use message_io::{
-    network::{Endpoint, NetEvent, RemoteAddr, Transport},
+    network::{Endpoint, NetEvent, RemoteAddr},
     node::{self, NodeEvent, NodeHandler, NodeListener},
 };
 use tokio::sync::mpsc;
+use tracing::warn;
 
-use crate::game::net::{ClientReceiver, ClientSender};
+pub type ClientReceiver = mpsc::UnboundedReceiver<Result<FromServerMessage, ClientError>>;
+pub type ClientSender = mpsc::UnboundedSender<FromClientMessage>;
 
 enum Signal {
     Ping,
@@ -18,23 +30,60 @@ enum Signal {
 
 const DISCONNECT_TIME: i64 = 4;
 
+/// Round trip probe id of our pings, only one is ever waiting for a pong
+const PING_PROBE: u64 = 0;
+
+/// Connection state shared between the sending and receiving tasks
+#[derive(Debug, Default)]
+struct SessionState {
+    token: Option<SessionToken>,
+    stats: NetStats,
+}
+
+type Session = Arc<Mutex<SessionState>>;
+
+/// Stamps a message with our session token and sequence number, encodes it and counts the bytes
+fn encode(session: &Session, message: FromClientMessage) -> Vec<u8> {
+    let mut session = session.lock().unwrap();
+    let message = ClientMessage {
+        token: session.token,
+        sequence: session.stats.next_sequence(),
+        message,
+    };
+    let data = codec::encode(&message);
+    session.stats.sent(data.len());
+    data
+}
+
 pub struct Client {
     handler: NodeHandler<Signal>,
     listener: Option<NodeListener<Signal>>,
+    network: Network<Signal>,
 
     server_id: Endpoint,
     local_addr: SocketAddr,
+
+    /// Token is filled in once the server accepts our join
+    session: Session,
+    /// Torn down without telling the server, see [Client::abandon]
+    abandoned: bool,
 }
 
 impl Drop for Client {
     fn drop(&mut self) {
-        self.stop();
+        if !self.abandoned {
+            self.stop();
+        }
     }
 }
 
 #[derive(Debug)]
 pub enum ClientError {
     Disconnected,
+    /// The server sent something we couldn't decode, most likely it's running a different version
+    InvalidMessage,
+    /// The server ended our session on purpose
+    Removed(DisconnectReason),
 }
 
 impl Display for ClientError {
@@ -43,6 +92,10 @@ impl Display for ClientError {
             ClientError::Disconnected => {
                 write!(f, "Client disconnected from server")
             }
+            ClientError::Removed(reason) => write!(f, "{reason}"),
+            ClientError::InvalidMessage => {
+                write!(f, "Received a message the client doesn't understand, the server is probably running a different version")
+            }
         }
     }
 }
@@ -50,16 +103,22 @@ impl Display for ClientError {
 impl Error for ClientError {}
 
 impl Client {
-    pub fn new(remote_addr: RemoteAddr) -> anyhow::Result<Self> {
+    pub fn new(remote_addr: RemoteAddr, transport: GameTransport) -> anyhow::Result<Self> {
         let (handler, listener) = node::split();
 
-        let (server_id, local_addr) = handler.network().connect(Transport::Udp, remote_addr)?;
+        // Connection based transports can't send anything until they're connected, UDP returns right away
+        let (server_id, local_addr) = handler
+            .network()
+            .connect_sync(transport.message_io(), remote_addr)?;
 
         Ok(Client {
+            network: Network::new(handler.clone()),
             handler,
             listener: Some(listener),
             server_id,
             local_addr,
+            session: Session::default(),
+            abandoned: false,
         })
     }
 
@@ -67,7 +126,41 @@ impl Client {
         self.handler.signals().send(Signal::Stop);
     }
 
+    /// Stops without sending Leave, so the server keeps our session around for resuming it
+    pub fn abandon(&mut self) {
+        self.abandoned = true;
+        self.handler.stop();
+    }
+
+    /// Sends everything through a simulated bad connection, must be called before starting
+    pub fn simulate(&mut self, conditions: NetConditions) {
+        self.network.simulate(conditions);
+    }
+
+    pub fn session_token(&self) -> Option<SessionToken> {
+        self.session.lock().unwrap().token
+    }
+
+    pub fn stats(&self) -> NetStatsSummary {
+        self.session.lock().unwrap().stats.summary()
+    }
+
     pub fn start(&mut self, username: &str) -> anyhow::Result<(ClientReceiver, ClientSender)> {
+        self.start_with(FromClientMessage::Join(JoinRequest::new(username)))
+    }
+
+    /// Like [Client::start], but joins as a spectator without a player
+    pub fn spectate(&mut self, username: &str) -> anyhow::Result<(ClientReceiver, ClientSender)> {
+        self.start_with(FromClientMessage::Join(JoinRequest::spectator(username)))
+    }
+
+    /// Like [Client::start], but reattaches to the player of a previous session
+    pub fn resume(&mut self, username: &str, token: SessionToken) -> anyhow::Result<(ClientReceiver, ClientSender)> {
+        self.session.lock().unwrap().token = Some(token);
+        self.start_with(FromClientMessage::Resume(JoinRequest::new(username)))
+    }
+
+    fn start_with(&mut self, join: FromClientMessage) -> anyhow::Result<(ClientReceiver, ClientSender)> {
         // Messages recieved
         let (from_server_sender, from_server_reciever) =
             mpsc::unbounded_channel::<Result<FromServerMessage, ClientError>>();
@@ -76,20 +169,24 @@ impl Client {
             mpsc::unbounded_channel::<FromClientMessage>();
 
         // Sends join event
-        from_client_sender.send(FromClientMessage::Join(username.to_string()))?;
+        from_client_sender.send(join)?;
 
         // Handles sent messages
-        let handler = self.handler.clone();
+        let network = self.network.clone();
         let server_id = self.server_id;
 
         let mut last_response: Option<DateTime<Utc>> = None;
 
+        let session = Arc::clone(&self.session);
+        let sender_session = Arc::clone(&session);
         tokio::spawn(async move {
             while let Some(message) = from_client_reciever.recv().await {
-                let output_data = bincode::serialize(&message).unwrap();
-                handler.network().send(server_id, &output_data);
+                let leaving = matches!(message, FromClientMessage::Leave);
 
-                if let FromClientMessage::Leave = message {
+                let output_data = encode(&sender_session, message);
+                network.send(server_id, &output_data);
+
+                if leaving {
                     break;
                 }
             }
@@ -100,13 +197,17 @@ impl Client {
         // Handles recieved messages
         let listener = self.listener.take().unwrap();
         let handler = self.handler.clone();
+        let network = self.network.clone();
         let local_addr = self.local_addr;
 
         let from_client_sender2 = from_client_sender.clone();
-        tokio::spawn(async move {
+        let mut reliable = ReliableReceiver::default();
+        let mut reassembler = Reassembler::default();
+        // The listener blocks until it's stopped, running it on a worker thread would starve the runtime with many clients
+        tokio::task::spawn_blocking(move || {
             listener.for_each(move |event| match event {
                 NodeEvent::Network(net_event) => match net_event {
-                    NetEvent::Connected(_, established) => { // pretty sure this never gets called as we are using udp
+                    NetEvent::Connected(_, established) => {
                         if established {
                             println!("Connected to server at {}", server_id.addr(),);
                             println!("Client identified by local port: {}", local_addr.port());
@@ -119,11 +220,58 @@ impl Client {
                     NetEvent::Message(_, input_data) => {
                         last_response = Some(Utc::now());
 
-                        from_server_sender
-                                .send(Ok(bincode::deserialize(input_data).unwrap())).expect("Failed to send message from server to client, this should never happen as messages shouldn't be sent here when listener has been closed")
+                        let packet: Packet = match codec::decode(input_data) {
+                            Ok(packet) => packet,
+                            Err(_) => {
+                                from_server_sender.send(Err(ClientError::InvalidMessage)).ok();
+                                return
+                            }
+                        };
+                        session.lock().unwrap().stats.received(input_data.len(), packet.sequence);
+
+                        let (packets, ack) = match packet.channel {
+                            Channel::Unreliable => (vec![packet], None),
+                            Channel::Reliable(sequence) => (reliable.receive(sequence, packet), Some(sequence)),
+                        };
+
+                        for data in packets.into_iter().filter_map(|packet| reassembler.receive(packet)) {
+                            let message = match compression::unpack(&data).map(|data| codec::decode(&data)) {
+                                Ok(Ok(message)) => Ok(message),
+                                // Sent by a newer server, there's nothing we could do with it anyway
+                                Ok(Err(CodecError::UnknownTag(tag))) => {
+                                    warn!("Ignored message with unknown tag {tag} from server");
+                                    continue;
+                                }
+                                Ok(Err(_)) | Err(_) => Err(ClientError::InvalidMessage),
+                            };
+                            match &message {
+                                Ok(FromServerMessage::JoinResponse(Ok(token))) => {
+                                    session.lock().unwrap().token = Some(*token);
+                                }
+                                // Anything sent with the old token would just be rejected again
+                                Ok(FromServerMessage::JoinResponse(Err(JoinRejection::SessionExpired))) => {
+                                    session.lock().unwrap().token = None;
+                                }
+                                Ok(FromServerMessage::Pong) => {
+                                    session.lock().unwrap().stats.answered_probe(PING_PROBE);
+                                }
+                                _ => {}
+                            }
+
+                            from_server_sender
+                                .send(message).expect("Failed to send message from server to client, this should never happen as messages shouldn't be sent here when listener has been closed")
+                        }
+
+                        // Ack every copy, the previous ack might have been lost.
+                        // Sent after handling the message, so the ack for our join already carries the token it gave us.
+                        if let Some(sequence) = ack {
+                            let output_data = encode(&session, FromClientMessage::Ack(sequence));
+                            network.send(server_id, &output_data);
+                        }
                     }
-                    NetEvent::Disconnected(_) => { // pretty sure this never gets called as we are using udp
+                    NetEvent::Disconnected(_) => { // only connection based transports notice this
                         println!("Server disconnected");
+                        from_server_sender.send(Err(ClientError::Disconnected)).ok();
                         handler.stop();
                     }
                 },
@@ -133,6 +281,11 @@ impl Client {
                             return
                         }
 
+                        let expired = reassembler.expire(REASSEMBLY_TIMEOUT);
+                        if expired > 0 {
+                            warn!("Dropped {expired} incomplete messages from server");
+                        }
+
                         if let Some(time) = last_response {
                             if time < (Utc::now() - Duration::seconds(DISCONNECT_TIME)) {
                                 from_server_sender.send(Err(ClientError::Disconnected)).unwrap();
@@ -140,8 +293,9 @@ impl Client {
                             }
                         }
 
-                        let output_data = bincode::serialize(&FromClientMessage::Ping).unwrap();
-                        handler.network().send(server_id, &output_data);
+                        let output_data = encode(&session, FromClientMessage::Ping);
+                        session.lock().unwrap().stats.sent_probe(PING_PROBE);
+                        network.send(server_id, &output_data);
                         handler
                             .signals()
                             .send_with_timer(Signal::Ping, time::Duration::from_secs(1));
@@ -2,15 +2,17 @@ use admin_client::program::Program;
 use anyhow::bail;
 use std::fmt::{Display, Formatter};
 use std::net::IpAddr;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use crate::client::ClientError;
 use crate::game::ecs::ClientEcs;
 use crate::game::net::Connection;
 use crate::game::Game;
 use crate::menu::Menu;
 use common::ecs::components::Player;
+use common::handshake::{JoinRejection, JoinRequest};
 use common::map::Map;
-use common::{FromClientMessage, FromServerMessage};
+use common::{FromClientMessage, FromServerMessage, SessionToken};
 use notan::app::{App, Graphics, Plugins};
 use notan::egui::{self, Align2, EguiPluginSugar};
 use notan::prelude::{Assets, Color};
@@ -18,16 +20,27 @@ use tracing::info;
 
 use crate::program::state::ProgramState;
 
+/// How long to wait for an answer before asking to join again, the request or its answer might have been lost
+const JOIN_RESEND: Duration = Duration::from_secs(1);
+
 pub struct Connecting {
     start_time: Instant,
+    /// When we last asked to join or resume
+    last_join: Instant,
     connection: Option<Connection>,
     ecs: Option<ClientEcs>,
     my_id: Option<u64>,
 
     exit: bool,
     recieved_own_id: bool,
+    /// The server confirmed we're watching without a player
+    spectating: bool,
 
     username: String,
+    /// Joining as a spectator instead of a player
+    spectate: bool,
+    /// Session we're trying to get back into, if any
+    resume: Option<SessionToken>,
 }
 
 impl Display for Connecting {
@@ -43,7 +56,41 @@ impl Connecting {
         server_ui: Option<Program>,
         username: &str,
     ) -> anyhow::Result<Self> {
-        let connection = Connection::new(address, port, username)?;
+        Self::connect(address, port, server_ui, username, false, None)
+    }
+
+    /// Joins without a player, to watch the game
+    pub fn spectate(
+        address: IpAddr,
+        port: u16,
+        server_ui: Option<Program>,
+        username: &str,
+    ) -> anyhow::Result<Self> {
+        Self::connect(address, port, server_ui, username, true, None)
+    }
+
+    /// Reconnects to the player of a previous session, falling back to a regular join if it has expired.
+    /// `spectate` only decides how to join again in that case, the server remembers what the session was.
+    pub fn resume(
+        address: IpAddr,
+        port: u16,
+        server_ui: Option<Program>,
+        username: &str,
+        spectate: bool,
+        token: SessionToken,
+    ) -> anyhow::Result<Self> {
+        Self::connect(address, port, server_ui, username, spectate, Some(token))
+    }
+
+    fn connect(
+        address: IpAddr,
+        port: u16,
+        server_ui: Option<Program>,
+        username: &str,
+        spectate: bool,
+        resume: Option<SessionToken>,
+    ) -> anyhow::Result<Self> {
+        let connection = Connection::new(address, port, username, spectate, resume)?;
         let mut ecs = ClientEcs::default();
 
         if let Some(su) = server_ui {
@@ -52,14 +99,18 @@ impl Connecting {
 
         Ok(Self {
             start_time: Instant::now(),
+            last_join: Instant::now(),
             connection: Some(connection),
             ecs: Some(ecs),
             my_id: None,
 
             exit: false,
             recieved_own_id: false,
+            spectating: false,
 
             username: username.to_string(),
+            spectate,
+            resume,
         })
     }
 }
@@ -72,6 +123,11 @@ impl ProgramState for Connecting {
         _plugins: &mut Plugins,
     ) -> anyhow::Result<()> {
         let connection = self.connection.as_mut().unwrap();
+        if !self.recieved_own_id && self.last_join.elapsed() >= JOIN_RESEND {
+            connection.send(join_message(&self.username, self.spectate, self.resume))?;
+            self.last_join = Instant::now();
+        }
+
         let message = match connection.receive() {
             Ok(Some(message)) => message,
             Ok(None) => return Ok(()),
@@ -81,21 +137,35 @@ impl ProgramState for Connecting {
         };
 
         match message {
+            FromServerMessage::JoinResponse(Ok(_)) => {
+                info!("Join accepted");
+            }
+            FromServerMessage::JoinResponse(Err(JoinRejection::SessionExpired)) if self.resume.is_some() => {
+                info!("Session expired, joining as a new player");
+                self.resume = None;
+                connection.send(join_message(&self.username, self.spectate, self.resume))?;
+                self.last_join = Instant::now();
+            }
+            FromServerMessage::JoinResponse(Err(rejection)) => {
+                bail!(rejection);
+            }
             FromServerMessage::OwnId(my_id) => {
                 self.recieved_own_id = true;
                 info!("Received OwnId");
                 self.my_id = Some(my_id);
             }
+            FromServerMessage::Spectating => {
+                // Stands in for OwnId, there's no player to wait for
+                self.recieved_own_id = true;
+                info!("Joined as a spectator");
+                self.spectating = true;
+            }
             FromServerMessage::SendMap(map) => {
                 info!("Received SendMap");
                 self.ecs.as_mut().unwrap().resources.insert(map);
             }
             FromServerMessage::Pong => {
                 info!("Pong");
-
-                if !self.recieved_own_id {
-                    connection.send(FromClientMessage::Join(self.username.to_string()))?;
-                }
             }
             FromServerMessage::EcsChanges(changes) => {
                 info!("Received EcsChanges");
@@ -103,6 +173,24 @@ impl ProgramState for Connecting {
                     self.ecs.as_mut().unwrap().handle_protocol(change)?;
                 }
             }
+            FromServerMessage::Snapshot(snapshot) => {
+                if let Some(tick) = self.ecs.as_mut().unwrap().apply_snapshot(snapshot)? {
+                    connection.send(FromClientMessage::AckSnapshot(tick))?;
+                }
+            }
+            FromServerMessage::Scoreboard(scoreboard) => {
+                self.ecs.as_mut().unwrap().resources.insert(scoreboard);
+            }
+            // Connection::receive already turns this into an error
+            FromServerMessage::Disconnect(reason) => {
+                bail!(ClientError::Removed(reason));
+            }
+            // Queries go through their own socket, so this is never meant for us
+            FromServerMessage::ServerInfo(_) => {}
+            // There's nowhere to show chat until the game starts
+            FromServerMessage::Chat(_) => {}
+            // Only sent to spectators asking to play, which we can't be yet
+            FromServerMessage::NoFreeSlot => {}
         }
 
         Ok(())
@@ -167,6 +255,14 @@ impl ProgramState for Connecting {
             return None;
         }
 
+        if self.spectating {
+            let ecs = self.ecs.take()?;
+            let connection = self.connection.take()?;
+            let game = Game::new(app, gfx, ecs, connection, None);
+
+            return Some(game.into());
+        }
+
         // Try to find the our player from the ecs
         let my_player_entity = self.my_id.and_then(|id| {
             for (entity, player) in self.ecs.as_mut()?.world.query_mut::<&Player>() {
@@ -181,8 +277,20 @@ impl ProgramState for Connecting {
 
         let ecs = self.ecs.take()?;
         let connection = self.connection.take()?;
-        let game = Game::new(app, gfx, ecs, connection, my_player_entity);
+        let game = Game::new(app, gfx, ecs, connection, Some(my_player_entity));
 
         Some(game.into())
     }
 }
+
+fn join_message(username: &str, spectate: bool, resume: Option<SessionToken>) -> FromClientMessage {
+    let request = if spectate {
+        JoinRequest::spectator(username)
+    } else {
+        JoinRequest::new(username)
+    };
+    match resume {
+        Some(_) => FromClientMessage::Resume(request),
+        None => FromClientMessage::Join(request),
+    }
+}
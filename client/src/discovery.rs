@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use common::defaults::DISCOVERY_PORT;
+use common::discovery::{DiscoveryProbe, DiscoveryResponse};
+use common::handshake::PROTOCOL_VERSION;
+
+use crate::poller::UdpPoller;
+
+/// How often the LAN is asked for servers again
+const PROBE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Servers that stop answering are forgotten after this long
+const SERVER_EXPIRY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct DiscoveredServer {
+    /// Address to join the game on
+    pub addr: SocketAddr,
+    pub info: DiscoveryResponse,
+    last_seen: Instant,
+}
+
+/// Finds servers on the local network by broadcasting probes to the discovery port
+pub struct LanDiscovery {
+    poller: UdpPoller,
+    servers: HashMap<SocketAddr, DiscoveredServer>,
+}
+
+impl LanDiscovery {
+    pub fn new() -> io::Result<Self> {
+        Ok(LanDiscovery {
+            poller: UdpPoller::broadcast(PROBE_INTERVAL)?,
+            servers: HashMap::new(),
+        })
+    }
+
+    /// Probes the LAN every probe interval and forgets servers that stopped answering
+    pub fn update(&mut self) -> io::Result<()> {
+        if self.poller.is_due() {
+            self.probe()?;
+        }
+
+        while let Some((data, from)) = self.poller.receive()? {
+            // Anything can be sent to our port, just ignore what isn't an answer
+            if let Ok(info) = bincode::deserialize::<DiscoveryResponse>(data) {
+                let addr = SocketAddr::new(from.ip(), info.port);
+                self.servers.insert(
+                    addr,
+                    DiscoveredServer {
+                        addr,
+                        info,
+                        last_seen: Instant::now(),
+                    },
+                );
+            }
+        }
+
+        self.servers
+            .retain(|_, server| server.last_seen.elapsed() < SERVER_EXPIRY);
+
+        Ok(())
+    }
+
+    /// Servers that answered recently, sorted by name
+    pub fn servers(&self) -> Vec<&DiscoveredServer> {
+        let mut servers: Vec<&DiscoveredServer> = self.servers.values().collect();
+        servers.sort_by(|a, b| a.info.name.cmp(&b.info.name).then(a.addr.cmp(&b.addr)));
+        servers
+    }
+
+    fn probe(&mut self) -> io::Result<()> {
+        let probe = DiscoveryProbe {
+            protocol_version: PROTOCOL_VERSION,
+        };
+        let data = bincode::serialize(&probe).expect("Probes should always be serializable");
+        self.poller.send(&data, (Ipv4Addr::BROADCAST, DISCOVERY_PORT))
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use common::ChatMessage;
+use notan::draw::{Draw, DrawTextSection, Font};
+use notan::prelude::Color;
+
+/// How long a message stays on screen
+const MESSAGE_DURATION: Duration = Duration::from_secs(8);
+/// Messages fade out over the end of their duration
+const FADE_DURATION: Duration = Duration::from_secs(2);
+/// Older messages are dropped once there are this many
+const MAX_MESSAGES: usize = 8;
+
+const FONT_SIZE: f32 = 16.0;
+const LINE_HEIGHT: f32 = 20.0;
+/// Distance of the newest line from the bottom of the screen, leaves room for the health bar
+const BOTTOM_OFFSET: f32 = 80.0;
+const LEFT_OFFSET: f32 = 10.0;
+
+/// Recent chat messages drawn over the game, fading away after a while
+pub struct ChatOverlay {
+    messages: VecDeque<(ChatMessage, Instant)>,
+    font: Font,
+}
+
+impl ChatOverlay {
+    pub fn new(font: Font) -> Self {
+        Self {
+            messages: VecDeque::new(),
+            font,
+        }
+    }
+
+    pub fn push(&mut self, message: ChatMessage) {
+        self.messages.push_back((message, Instant::now()));
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+
+    /// Draws the messages with the newest at the bottom.
+    /// While typing, the input goes below them and every message is shown in full.
+    pub fn draw(&self, draw: &mut Draw, typing: Option<&str>, height: usize) {
+        let mut y = height as f32 - BOTTOM_OFFSET;
+
+        if let Some(text) = typing {
+            self.draw_line(draw, &format!("> {text}_"), y, 1.0);
+            y -= LINE_HEIGHT;
+        }
+
+        for (message, received) in self.messages.iter().rev() {
+            let alpha = if typing.is_some() {
+                1.0
+            } else {
+                fade(received.elapsed())
+            };
+            if alpha <= 0.0 {
+                continue;
+            }
+
+            self.draw_line(draw, &format!("{}: {}", message.name, message.text), y, alpha);
+            y -= LINE_HEIGHT;
+        }
+    }
+
+    fn draw_line(&self, draw: &mut Draw, text: &str, y: f32, alpha: f32) {
+        // Shadow, so the text stays readable on bright walls
+        draw.text(&self.font, text)
+            .position(LEFT_OFFSET + 1.0, y + 1.0)
+            .size(FONT_SIZE)
+            .color(Color::new(0.0, 0.0, 0.0, alpha));
+        draw.text(&self.font, text)
+            .position(LEFT_OFFSET, y)
+            .size(FONT_SIZE)
+            .color(Color::new(1.0, 1.0, 1.0, alpha));
+    }
+}
+
+/// Opacity of a message of the given age
+fn fade(age: Duration) -> f32 {
+    let remaining = MESSAGE_DURATION.saturating_sub(age);
+    (remaining.as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.0)
+}
@@ -11,4 +11,7 @@ pub struct RenderSprite {
 pub struct Scale (pub Vec2);
 pub struct Height (pub f32);
 
-pub struct ClientSide;
\ No newline at end of file
+pub struct ClientSide;
+/// Latest position of our own player according to the server.
+/// Kept apart from [Position](common::ecs::components::Position) so it doesn't overwrite the predicted one.
+pub struct ServerPosition (pub Vec2);
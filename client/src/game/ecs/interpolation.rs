@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use common::defaults::{PLAYER_SPEED, TICKS_PER_SECOND};
+use common::Tick;
+use glam::Vec2;
+
+/// Default for how far behind the estimated server time remote entities are rendered
+const DEFAULT_INTERPOLATION_WINDOW: Duration = Duration::from_millis(100);
+
+/// A jump further than this between two samples is a teleport, like a respawn, and isn't interpolated.
+/// Twice what a player can walk within the default window, so the slowest snapshots still glide.
+const TELEPORT_DISTANCE: f32 = 2.0 * PLAYER_SPEED * DEFAULT_INTERPOLATION_WINDOW.as_millis() as f32 / 1000.0;
+
+/// Upper bound on buffered samples, in case the render time falls far behind
+const MAX_SAMPLES: usize = 64;
+
+/// If our clock drifts further than this from the snapshots, we jump to the snapshot time
+const MAX_CLOCK_DRIFT: f64 = 1.0;
+
+/// How quickly the clock is pulled towards the time of a newly received snapshot
+const CLOCK_CORRECTION: f64 = 0.1;
+
+/// Positions of a remote entity received from the server, stamped with server time in seconds
+#[derive(Debug, Default)]
+pub struct PositionBuffer(VecDeque<(f64, Vec2)>);
+
+impl PositionBuffer {
+    pub fn new(time: f64, pos: Vec2) -> Self {
+        PositionBuffer(VecDeque::from([(time, pos)]))
+    }
+
+    pub fn push(&mut self, time: f64, pos: Vec2) {
+        // Changes from the same snapshot replace each other, older ones are out of date
+        match self.0.back_mut() {
+            Some((last, last_pos)) if *last == time => {
+                *last_pos = pos;
+                return;
+            }
+            Some((last, _)) if *last > time => return,
+            // Sliding there would go straight through walls, so it shows up at the new place right away
+            Some((_, last_pos)) if last_pos.distance(pos) > TELEPORT_DISTANCE => self.0.clear(),
+            _ => {}
+        }
+
+        if self.0.len() >= MAX_SAMPLES {
+            self.0.pop_front();
+        }
+        self.0.push_back((time, pos));
+    }
+
+    /// Position at the given time, clamped to the oldest and newest samples.
+    /// Samples no longer needed for future render times are discarded.
+    pub fn sample(&mut self, time: f64) -> Option<Vec2> {
+        while self.0.len() > 1 && self.0[1].0 <= time {
+            self.0.pop_front();
+        }
+
+        let &(from_time, from) = self.0.front()?;
+        let (to_time, to) = match self.0.get(1) {
+            Some(&next) if time > from_time => next,
+            _ => return Some(from),
+        };
+
+        let t = (time - from_time) / (to_time - from_time);
+        Some(from.lerp(to, t as f32))
+    }
+}
+
+/// Keeps an estimate of the server clock to render remote entities slightly in the past.
+/// Rendering in the past means there are usually two snapshots to interpolate between,
+/// which hides jitter and the occasional lost packet.
+#[derive(Debug)]
+pub struct Interpolation {
+    server_time: Option<f64>,
+    window: f64,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation {
+            server_time: None,
+            window: DEFAULT_INTERPOLATION_WINDOW.as_secs_f64(),
+        }
+    }
+}
+
+impl Interpolation {
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window.as_secs_f64();
+    }
+
+    /// Server time in seconds of a given tick
+    pub fn tick_time(tick: Tick) -> f64 {
+        tick as f64 / TICKS_PER_SECOND as f64
+    }
+
+    /// Pulls the clock towards the time of a snapshot we just received
+    pub fn on_snapshot(&mut self, tick: Tick) {
+        let snapshot_time = Self::tick_time(tick);
+
+        self.server_time = Some(match self.server_time {
+            Some(time) if (snapshot_time - time).abs() < MAX_CLOCK_DRIFT => {
+                time + (snapshot_time - time) * CLOCK_CORRECTION
+            }
+            _ => snapshot_time,
+        });
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        if let Some(time) = &mut self.server_time {
+            *time += dt as f64;
+        }
+    }
+
+    /// Time remote entities should currently be rendered at
+    pub fn render_time(&self) -> Option<f64> {
+        self.server_time.map(|time| time - self.window)
+    }
+
+    /// Newest tick at or before the render time, what the server rewinds to when checking our hits
+    pub fn render_tick(&self) -> Option<Tick> {
+        self.render_time()
+            .map(|time| (time * TICKS_PER_SECOND as f64).max(0.0) as Tick)
+    }
+}
@@ -1,10 +1,15 @@
 use std::collections::HashMap;
 use std::num::NonZeroU64;
+use common::Tick;
 use hecs::{Entity, World};
 use resources::Resources;
+use crate::game::ecs::interpolation::Interpolation;
+use crate::game::ecs::prediction::PendingInputs;
 use crate::game::ecs::systems::ClientSystems;
 
+pub mod interpolation;
 pub mod net;
+mod prediction;
 pub mod systems;
 pub mod component;
 
@@ -15,6 +20,15 @@ pub struct ClientEcs {
 
     /// Keeps track of what server entity maps to what client entity
     entity_map: HashMap<NonZeroU64, Entity>,
+
+    /// Tick of the newest snapshot that has been applied
+    snapshot_tick: Option<Tick>,
+
+    /// Our own inputs that have been predicted but not yet applied by the server
+    pending_inputs: PendingInputs,
+
+    /// Clock used to render remote entities slightly in the past
+    pub interpolation: Interpolation,
 }
 
 impl ClientEcs {
@@ -22,6 +36,18 @@ impl ClientEcs {
     pub fn tick(&mut self, dt: f32) {
         ClientSystems::run(self, dt);
     }
+
+    pub fn snapshot_tick(&self) -> Option<Tick> {
+        self.snapshot_tick
+    }
+
+    /// Tick remote entities are currently shown at, sent along with our inputs for lag compensation
+    pub fn view_tick(&self) -> Tick {
+        self.interpolation
+            .render_tick()
+            .or(self.snapshot_tick)
+            .unwrap_or(0)
+    }
 }
 
 pub struct MyEntity(pub Entity);
\ No newline at end of file
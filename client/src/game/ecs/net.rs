@@ -1,11 +1,16 @@
 use std::num::NonZeroU64;
 
 use anyhow::Result;
-use hecs::Entity;
+use glam::Vec2;
+use hecs::{ComponentError, Entity};
 
-use common::ecs::components::EcsProtocol;
+use common::ecs::components::{EcsProtocol, InsertComponent, Position};
+use common::snapshot::Snapshot;
+use common::Tick;
 
-use crate::game::ecs::ClientEcs;
+use crate::game::ecs::component::ServerPosition;
+use crate::game::ecs::interpolation::{Interpolation, PositionBuffer};
+use crate::game::ecs::{ClientEcs, MyEntity};
 
 impl ClientEcs {
     /// Retrieves a mapped entity or reserves a new one
@@ -14,16 +19,51 @@ impl ClientEcs {
             .or_insert_with_key(|_| self.world.reserve_entity())
     }
 
+    /// Whether the server entity is our own player
+    fn is_mine(&self, entity_id: NonZeroU64) -> bool {
+        match self.resources.get::<MyEntity>() {
+            Ok(my_entity) => self.entity_map.get(&entity_id) == Some(&my_entity.0),
+            Err(_) => false,
+        }
+    }
+
+    /// Remote positions go through a [PositionBuffer] so they can be interpolated.
+    /// Entities we haven't seen a position for yet get placed right away.
+    fn buffer_position(&mut self, entity: Entity, pos: Vec2) -> Result<()> {
+        let time = self.snapshot_tick.map_or(0.0, Interpolation::tick_time);
+
+        if let Ok(buffer) = self.world.query_one_mut::<&mut PositionBuffer>(entity) {
+            buffer.push(time, pos);
+        } else {
+            self.world.insert(entity, (Position(pos), PositionBuffer::new(time, pos)))?;
+        }
+        Ok(())
+    }
+
     /// Handle an [EcsProtocol] message from the server
     pub fn handle_protocol(&mut self, message: EcsProtocol) -> Result<()> {
         match message {
+            EcsProtocol::Insert((entity_id, InsertComponent::Position(pos))) if self.is_mine(entity_id) => {
+                // Our own position is predicted locally, the server one only gets used for reconciliation
+                let entity = self.map_entity(entity_id);
+                self.world.insert_one(entity, ServerPosition(pos.0))?;
+            }
+            EcsProtocol::Insert((entity_id, InsertComponent::Position(pos))) => {
+                let entity = self.map_entity(entity_id);
+                self.buffer_position(entity, pos.0)?;
+            }
             EcsProtocol::Insert((entity_id, insert)) => {
                 let entity = self.map_entity(entity_id);
                 insert.apply(&mut self.world, entity)?;
             }
             EcsProtocol::Remove((entity_id, remove)) => {
-                let entity = self.map_entity(entity_id);
-                remove.apply(&mut self.world, entity)?;
+                if let Some(&entity) = self.entity_map.get(&entity_id) {
+                    // The component can already be gone if we got a newer snapshot before
+                    match remove.apply(&mut self.world, entity) {
+                        Ok(()) | Err(ComponentError::MissingComponent(_)) => {}
+                        Err(err) => return Err(err.into()),
+                    }
+                }
             }
             EcsProtocol::Despawn(entity_id) => {
                 if let Some(entity) = self.entity_map.remove(&entity_id) {
@@ -33,4 +73,36 @@ impl ClientEcs {
         }
         Ok(())
     }
+
+    /// Apply a [Snapshot] from the server.
+    /// Returns the tick to acknowledge if the snapshot was fully applied.
+    ///
+    /// Changes to entities we don't know about yet are skipped, since their spawn is still on its way
+    /// over the reliable channel. The snapshot isn't acknowledged then, so the server keeps sending those changes.
+    pub fn apply_snapshot(&mut self, snapshot: Snapshot) -> Result<Option<Tick>> {
+        if self.snapshot_tick.map_or(false, |tick| snapshot.tick <= tick) {
+            return Ok(None);
+        }
+
+        self.snapshot_tick = Some(snapshot.tick);
+        self.interpolation.on_snapshot(snapshot.tick);
+
+        let mut complete = true;
+        for change in snapshot.changes {
+            let entity_id = match &change {
+                EcsProtocol::Insert((entity_id, _))
+                | EcsProtocol::Remove((entity_id, _))
+                | EcsProtocol::Despawn(entity_id) => *entity_id,
+            };
+
+            if !self.entity_map.contains_key(&entity_id) {
+                complete = false;
+                continue;
+            }
+
+            self.handle_protocol(change)?;
+        }
+
+        Ok(complete.then_some(snapshot.tick))
+    }
 }
@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+
+use common::defaults::PLAYER_SPEED;
+use common::ecs::components::{InputState, LastInput, Position};
+use common::ecs::system::movement;
+use common::map::Map;
+use common::net::Sequence;
+
+use crate::game::ecs::component::ServerPosition;
+use crate::game::ecs::{ClientEcs, MyEntity};
+
+/// How many unacknowledged inputs we keep around for replaying.
+/// Anything older than this is so far behind that the server position is the better guess anyway.
+const MAX_PENDING_INPUTS: usize = 1024;
+
+/// An input we've already applied locally but the server hasn't confirmed yet.
+/// Every input stands for one tick, so there's no frame time to remember.
+#[derive(Debug, Clone, Copy)]
+struct PendingInput {
+    sequence: Sequence,
+    input: InputState,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct PendingInputs(VecDeque<PendingInput>);
+
+impl ClientEcs {
+    /// Applies our own input immediately instead of waiting for the server to echo it back.
+    /// The input is remembered so it can be replayed on top of the next server position.
+    pub fn predict(&mut self, sequence: Sequence, input: InputState) {
+        let pending = &mut self.pending_inputs.0;
+        if pending.len() >= MAX_PENDING_INPUTS {
+            pending.pop_front();
+        }
+        pending.push_back(PendingInput { sequence, input });
+
+        let my_entity = self.resources.get::<MyEntity>().unwrap().0;
+        let map = self.resources.get::<Map>().unwrap();
+
+        if let Ok(pos) = self.world.query_one_mut::<&mut Position>(my_entity) {
+            pos.0 = movement::step(&map, pos.0, &input, PLAYER_SPEED);
+        }
+    }
+
+    /// Corrects our predicted position with the newest one from the server.
+    ///
+    /// Inputs the server has already applied are forgotten,
+    /// the rest get replayed on top of the server position.
+    pub fn reconcile(&mut self) {
+        let my_entity = self.resources.get::<MyEntity>().unwrap().0;
+
+        if let Ok(last_input) = self.world.query_one_mut::<&LastInput>(my_entity) {
+            let last_input = last_input.0;
+            let pending = &mut self.pending_inputs.0;
+            while pending.front().map_or(false, |p| p.sequence <= last_input) {
+                pending.pop_front();
+            }
+        }
+
+        // Nothing to correct until the server sends a new position
+        let server_pos = match self.world.remove_one::<ServerPosition>(my_entity) {
+            Ok(server_pos) => server_pos.0,
+            Err(_) => return,
+        };
+
+        let map = self.resources.get::<Map>().unwrap();
+        let predicted = self
+            .pending_inputs
+            .0
+            .iter()
+            .fold(server_pos, |pos, p| movement::step(&map, pos, &p.input, PLAYER_SPEED));
+
+        if let Ok(pos) = self.world.query_one_mut::<&mut Position>(my_entity) {
+            pos.0 = predicted;
+        }
+    }
+}
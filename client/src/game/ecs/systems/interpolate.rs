@@ -0,0 +1,27 @@
+use common::ecs::components::Position;
+use crate::game::ecs::{ClientEcs, MyEntity};
+use crate::game::ecs::interpolation::PositionBuffer;
+use crate::game::ecs::systems::ClientSystems;
+
+impl ClientSystems {
+    /// Move remote entities along their received positions, delayed by the interpolation window
+    pub fn interpolate_positions(ecs: &mut ClientEcs, dt: f32) {
+        ecs.interpolation.advance(dt);
+        let render_time = match ecs.interpolation.render_time() {
+            Some(time) => time,
+            None => return,
+        };
+
+        // Our own position is predicted instead
+        let my_entity = ecs.resources.get::<MyEntity>().ok().map(|e| e.0);
+
+        ecs.world.query_mut::<(&mut PositionBuffer, &mut Position)>()
+            .into_iter()
+            .filter(|(entity, _)| Some(*entity) != my_entity)
+            .for_each(|(_entity, (buffer, pos))| {
+                if let Some(sampled) = buffer.sample(render_time) {
+                    pos.0 = sampled;
+                }
+            })
+    }
+}
@@ -1,6 +1,7 @@
 use crate::game::ecs::ClientEcs;
 
 mod physics;
+mod interpolate;
 pub mod animation;
 mod client_init;
 
@@ -10,6 +11,7 @@ pub struct ClientSystems;
 impl ClientSystems {
     pub fn run(ecs: &mut ClientEcs, dt: f32) {
         ClientSystems::apply_velocity(ecs, dt);
+        ClientSystems::interpolate_positions(ecs, dt);
         ClientSystems::client_init(ecs, dt);
         ClientSystems::animate_running(ecs, dt);
         ClientSystems::apply_animations(ecs, dt);
@@ -65,6 +65,10 @@ impl GameUI {
         self.game_state = game_state
     }
 
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
     pub fn draw_health(&self, draw: &mut notan::draw::Draw, _width: usize, height: usize) {
         let proc = self.game_state.player_hp / self.game_state.player_hp_max;
         let health_color = if proc > 0.5 {
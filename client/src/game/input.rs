@@ -2,12 +2,20 @@ use std::f32::consts::PI;
 use glam::{IVec2, Vec2};
 use notan::Event;
 use notan::prelude::{App, KeyCode, MouseButton};
+use common::defaults::MAX_CHAT_LENGTH;
 use common::ecs::components::InputState;
 
 const MOUSE_SENSITIVITY: f32 = 3.0 / 10000.0;
 const KB_LOOK_SENSITIVITY: f32 = 2.5;
 const UP_DOWN_ANGLE_CLAMP: f32 = 45.0 / 180.0 * PI;
 
+/// Keys that only do something while spectating
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectatorAction {
+    FollowNext,
+    Play,
+}
+
 pub struct InputHandler {
     dirty: bool,
     state: InputState,
@@ -17,6 +25,13 @@ pub struct InputHandler {
     mouse_locked: bool,
     slow_look: bool,
 
+    /// Text typed into the chat so far, None while not chatting
+    chat: Option<String>,
+    /// Finished chat message waiting to be sent
+    chat_message: Option<String>,
+    /// Spectator keys pressed since the last frame
+    spectator_actions: Vec<SpectatorAction>,
+
     #[cfg(feature = "mouse-look")]
     mouse: MouseController,
 }
@@ -29,6 +44,9 @@ impl InputHandler {
             up_down_angle: 0.0,
             mouse_locked: false,
             slow_look: false,
+            chat: None,
+            chat_message: None,
+            spectator_actions: Vec::new(),
             #[cfg(feature = "mouse-look")]
             mouse: MouseController::new(_app),
         }
@@ -46,10 +64,30 @@ impl InputHandler {
         self.state
     }
 
+    /// Text typed into the chat so far, None while not chatting
+    pub fn chat_input(&self) -> Option<&str> {
+        self.chat.as_deref()
+    }
+
+    /// Returns the chat message the player finished typing, if there is one
+    pub fn take_chat_message(&mut self) -> Option<String> {
+        self.chat_message.take()
+    }
+
+    /// Returns the spectator keys pressed since the last call, players can ignore them
+    pub fn take_spectator_actions(&mut self) -> Vec<SpectatorAction> {
+        std::mem::take(&mut self.spectator_actions)
+    }
+
     pub fn tick(&mut self, app: &mut App) {
         let dt = app.timer.delta_f32();
         self.up_down_angle = lerp(self.up_down_angle, 0.0, 5.0 * dt);
 
+        // The arrow keys belong to the chat while typing
+        if self.chat.is_some() {
+            return
+        }
+
         let mut look = Vec2::ZERO;
         if app.keyboard.is_down(KeyCode::Right) {
             look.x -= 1.0;
@@ -93,6 +131,11 @@ impl InputHandler {
 
     /// Handles a given event.
     pub fn handle_event(&mut self, event: Event) {
+        if self.chat.is_some() {
+            self.handle_chat_event(event);
+            return
+        }
+
         let state_dirtied = match event {
             Event::MouseWheel { .. } => return,
             Event::KeyDown {key} => {
@@ -156,12 +199,62 @@ impl InputHandler {
                 self.mouse_locked = false;
                 return false;
             }
+            KeyCode::Return if pressed => {
+                self.open_chat();
+            }
+            KeyCode::F if pressed => {
+                self.spectator_actions.push(SpectatorAction::FollowNext);
+                return false;
+            }
+            KeyCode::P if pressed => {
+                self.spectator_actions.push(SpectatorAction::Play);
+                return false;
+            }
             _ => return false,
         }
 
         true
     }
 
+    /// Starts typing a chat message, the player stops moving and shooting until it's done
+    fn open_chat(&mut self) {
+        self.chat = Some(String::new());
+        self.state = InputState {
+            look_angle: self.state.look_angle,
+            ..Default::default()
+        };
+        self.slow_look = false;
+    }
+
+    fn handle_chat_event(&mut self, event: Event) {
+        let chat = match &mut self.chat {
+            Some(chat) => chat,
+            None => return,
+        };
+
+        match event {
+            // Enter and backspace come through as characters too
+            Event::ReceivedCharacter(character) if !character.is_control() => {
+                if chat.chars().count() < MAX_CHAT_LENGTH {
+                    chat.push(character);
+                }
+            }
+            Event::KeyDown { key: KeyCode::Back } => {
+                chat.pop();
+            }
+            Event::KeyDown { key: KeyCode::Return } => {
+                let text = self.chat.take().unwrap_or_default();
+                if !text.trim().is_empty() {
+                    self.chat_message = Some(text);
+                }
+            }
+            Event::KeyDown { key: KeyCode::Escape } => {
+                self.chat = None;
+            }
+            _ => {}
+        }
+    }
+
     fn handle_click(&mut self, button: MouseButton, pressed: bool) -> bool {
         match button {
             MouseButton::Left => {
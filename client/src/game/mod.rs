@@ -1,52 +1,72 @@
+mod chat;
 pub(crate) mod ecs;
 mod gameui;
 mod input;
 mod minimap;
 pub(crate) mod net;
 mod raycast;
+mod spectator;
 mod texture;
 
 use crate::args::ARGS;
+use crate::client::ClientError;
+use crate::connecting::Connecting;
+use crate::error::ErrorState;
 use crate::game::minimap::Minimap;
 use crate::game::raycast::*;
 use crate::program::state::ProgramState;
 use admin_client::program::Program;
-use common::defaults::{MINIMAP_SCALE, PLAYER_MAX_HP};
+use common::defaults::{MINIMAP_SCALE, PLAYER_MAX_HP, TICK_DURATION};
 use notan::app::{App, Color, Graphics, Plugins};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use notan::draw::CreateDraw;
 use notan::prelude::*;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 
 use notan::egui::{Color32, EguiPluginSugar, Frame, Grid, Pos2, Ui, Window};
 
 use crate::game::ecs::component::{Height, RenderSprite, Scale};
 use crate::game::ecs::{ClientEcs, MyEntity};
-use crate::game::input::InputHandler;
+use crate::game::input::{InputHandler, SpectatorAction};
 use crate::game::net::Connection;
 use crate::game::raycast::sprites::Sprite;
 use crate::game::texture::pixels::Pixels;
-use common::ecs::components::{Deaths, Health, HeldWeapon, Kills, Player, Position};
+use common::ecs::components::{Health, HeldWeapon, Position};
 use common::map::Map;
-use common::{FromClientMessage, FromServerMessage};
+use common::net::Sequence;
+use common::{FromClientMessage, FromServerMessage, Scoreboard};
 use fps_counter::FPSCounter;
 use glam::Vec2;
 use hecs::Entity;
 use itertools::Itertools;
 
+use self::chat::ChatOverlay;
 use self::gameui::{GameUI, GameUiState};
+use self::spectator::Spectator;
 
 const CAMERA_SENSITIVITY: f32 = 0.08; // rad
 const FOV: f32 = 70.0;
 const CEILING_COLOR: [u8; 4] = [110, 110, 190, 255];
 const FLOOR_COLOR: [u8; 4] = [90, 90, 90, 255];
+/// Most ticks worth of inputs sent in one frame, a longer hitch just loses the rest
+const MAX_INPUT_TICKS_PER_FRAME: u32 = 8;
 
 pub struct Game {
     ecs: ClientEcs,
     connection: Connection,
+    /// Our player, or the spectator camera while spectating
     my_entity: Entity,
+    /// Set while watching without a player
+    spectator: Option<Spectator>,
     input: InputHandler,
+    /// Sequence number of the last input sent to the server
+    input_sequence: Sequence,
+    /// Frame time not yet turned into inputs, one input gets sent per tick of it
+    input_time: f32,
+    /// The input changed since the last one was sent
+    input_dirty: bool,
 
     pixels: Pixels,
     minimap: Minimap,
@@ -55,21 +75,41 @@ pub struct Game {
     fps: FPSCounter,
 
     ui: GameUI,
+    chat: ChatOverlay,
     profiler: bool,
+
+    /// Lost connection to the server, try to resume the session
+    reconnect: bool,
 }
 
 impl Game {
+    /// Starts playing as the given player, or spectating if there is none
     pub fn new(
         app: &mut App,
         gfx: &mut Graphics,
         mut ecs: ClientEcs,
         connection: Connection,
-        my_entity: Entity,
+        player: Option<Entity>,
     ) -> Self {
         let (width, height) = gfx.size();
         let (width, height) = (width as usize, height as usize);
 
+        let ui = GameUI::new(GameUiState::new(), gfx);
+        let chat = ChatOverlay::new(ui.font().clone());
+
+        let spectator = match player {
+            Some(_) => None,
+            None => {
+                let map = ecs.resources.get::<Map>().unwrap();
+                Some(Spectator::new(&mut ecs.world, &map, ui.font().clone()))
+            }
+        };
+        // The camera stands in for our player, so everything that looks from our point of view keeps working
+        let my_entity = player.unwrap_or_else(|| spectator.as_ref().unwrap().camera());
+
         ecs.resources.insert(MyEntity(my_entity));
+        ecs.interpolation
+            .set_window(Duration::from_millis(ARGS.interpolation_window));
 
         let pixels = Pixels::new(width, height, gfx);
         let mut minimap = Minimap::new(ecs.resources.get::<Map>().unwrap().clone(), gfx);
@@ -81,15 +121,17 @@ impl Game {
 
         let ray_caster = RayCaster::new(width, height, FOV);
 
-        let ui = GameUI::new(GameUiState::new(), gfx);
-
         let input = InputHandler::new(app);
 
         Game {
             ecs,
             connection,
             my_entity,
+            spectator,
             input,
+            input_sequence: 0,
+            input_time: 0.0,
+            input_dirty: false,
 
             pixels,
             minimap,
@@ -97,7 +139,9 @@ impl Game {
             ray_caster,
             fps,
             ui,
+            chat,
             profiler: false,
+            reconnect: false,
         }
     }
 }
@@ -115,15 +159,68 @@ impl ProgramState for Game {
         _assets: &mut Assets,
         _plugins: &mut Plugins,
     ) -> anyhow::Result<()> {
-        self.accept_messages()?;
+        if let Err(err) = self.accept_messages() {
+            // A dropped connection can be resumed, anything else ends the game
+            match err.downcast_ref::<ClientError>() {
+                Some(ClientError::Disconnected) if self.connection.session_token().is_some() => {
+                    self.reconnect = true;
+                    return Ok(());
+                }
+                _ => return Err(err),
+            }
+        }
+        self.ecs.reconcile();
+
+        let dt = app.system_timer.delta_f32();
 
         self.input.tick(app);
-        if let Some(state) = self.input.take_state() {
-            self.connection
-                .send(FromClientMessage::UpdateInputs(state))?;
+        if let Some(text) = self.input.take_chat_message() {
+            self.connection.send(FromClientMessage::Chat(text))?;
+        }
+        let actions = self.input.take_spectator_actions();
+        let dirty = self.input.take_state().is_some();
+        let state = self.input.peek_state();
+
+        if let Some(spectator) = &mut self.spectator {
+            for action in actions {
+                match action {
+                    SpectatorAction::FollowNext => spectator.follow_next(&mut self.ecs.world),
+                    SpectatorAction::Play => self.connection.send(FromClientMessage::Play)?,
+                }
+            }
+
+            // The server doesn't simulate spectators, so the inputs stay local
+            spectator.update(&mut self.ecs.world, &*self.ecs.resources.get::<Map>()?, &state, dt);
+
+            if let Some(player) = spectator.joined_player(&mut self.ecs.world) {
+                self.start_playing(player);
+            }
+        } else {
+            self.input_dirty |= dirty;
+            self.input_time += dt;
+
+            // Each input stands for exactly one server tick, so the server simulates what we predict
+            let mut ticks = 0;
+            while self.input_time >= TICK_DURATION && ticks < MAX_INPUT_TICKS_PER_FRAME {
+                self.input_time -= TICK_DURATION;
+                ticks += 1;
+
+                if self.input_dirty || state.is_moving() {
+                    self.input_dirty = false;
+                    self.input_sequence += 1;
+                    self.connection.send(FromClientMessage::UpdateInputs(
+                        self.input_sequence,
+                        state,
+                        self.ecs.view_tick(),
+                    ))?;
+                    self.ecs.predict(self.input_sequence, state);
+                }
+            }
+            if ticks == MAX_INPUT_TICKS_PER_FRAME {
+                self.input_time = 0.0;
+            }
         }
 
-        let dt = app.system_timer.delta_f32();
         self.ecs.tick(dt);
 
         Ok(())
@@ -151,7 +248,13 @@ impl ProgramState for Game {
             .query_one_mut::<&Position>(self.my_entity)
             .context("Couldn't query for own player entity")?;
         let my_pos = my_pos.0;
-        let my_dir = Vec2::from_angle(self.input.peek_state().look_angle);
+        let my_dir = self
+            .spectator
+            .as_ref()
+            .and_then(|spectator| spectator.look_direction(&self.ecs.world))
+            .unwrap_or_else(|| Vec2::from_angle(self.input.peek_state().look_angle));
+        // The followed player is where the camera is, so they're left out like our own player
+        let following = self.spectator.as_ref().and_then(Spectator::following);
 
         self.pixels.clear_with_column(|y| {
             if y <= horizon {
@@ -177,7 +280,7 @@ impl ProgramState for Game {
             .world
             .query_mut::<(&Position, &RenderSprite, Option<&Scale>, Option<&Height>)>()
             .into_iter()
-            .filter(|(entity, _)| self.my_entity != *entity)
+            .filter(|(entity, _)| self.my_entity != *entity && following != Some(*entity))
             .map(|(_, (pos, sprite, scale, height))| {
                 (
                     pos.0,
@@ -198,22 +301,27 @@ impl ProgramState for Game {
         self.pixels.flush(gfx);
         self.pixels.draw(&mut draw);
 
-        // set UI game state
-        let (health, weapon) = self
+        // set UI game state, spectators see the followed player's and nothing while flying freely
+        if let Ok((health, weapon)) = self
             .ecs
             .world
-            .query_one_mut::<(&Health, &HeldWeapon)>(self.my_entity)
-            .unwrap();
-        self.ui.set_game_state(GameUiState {
-            player_hp_max: PLAYER_MAX_HP,
-            player_hp: health.0,
-            weapon_name: weapon.gun.to_string(),
-            max_ammo: weapon.gun.max_ammo(),
-            ammo: weapon.ammo,
-        });
-        // Draw UI
-        self.ui.draw_health(&mut draw, width, height);
-        self.ui.draw_weapon_stats(&mut draw, width, height);
+            .query_one_mut::<(&Health, &HeldWeapon)>(following.unwrap_or(self.my_entity))
+        {
+            self.ui.set_game_state(GameUiState {
+                player_hp_max: PLAYER_MAX_HP,
+                player_hp: health.0,
+                weapon_name: weapon.gun.to_string(),
+                max_ammo: weapon.gun.max_ammo(),
+                ammo: weapon.ammo,
+            });
+            // Draw UI
+            self.ui.draw_health(&mut draw, width, height);
+            self.ui.draw_weapon_stats(&mut draw, width, height);
+        }
+        if let Some(spectator) = &self.spectator {
+            spectator.draw(&mut draw, &self.ecs.world);
+        }
+        self.chat.draw(&mut draw, self.input.chat_input(), height);
 
         // Drawing minimap
         self.minimap.draw(&mut draw, width, height);
@@ -281,18 +389,22 @@ impl ProgramState for Game {
                         .resizable(false)
                         .fixed_pos(Pos2 { x: 550.0, y: 5.0 })
                         .show(ctx, |ui| {
-                            for (_, (player, kills, deaths)) in
-                                self.ecs.world.query::<(&Player, &Kills, &Deaths)>().iter()
-                            {
-                                let kd: f32 = if kills.0 == 0 || deaths.0 == 0 {
+                            // Players out of sight aren't replicated, so this comes from the server separately
+                            let scoreboard = self.ecs.resources.get::<Scoreboard>();
+                            for entry in scoreboard.iter().flat_map(|s| s.0.iter()) {
+                                let kd: f32 = if entry.kills == 0 || entry.deaths == 0 {
                                     0.0
                                 } else {
-                                    kills.0 as f32 / deaths.0 as f32
+                                    entry.kills as f32 / entry.deaths as f32
                                 };
 
+                                let ping = entry
+                                    .ping
+                                    .map_or_else(|| "-".to_string(), |ping| ping.to_string());
+
                                 ui.label(format!(
-                                    "[{}] K: {}, D: {}, K/D: {kd}",
-                                    player.name, kills.0, deaths.0,
+                                    "[{}] K: {}, D: {}, K/D: {kd}, Ping: {ping}",
+                                    entry.name, entry.kills, entry.deaths,
                                 ));
                             }
                         });
@@ -321,26 +433,119 @@ impl ProgramState for Game {
         self.input.handle_event(event);
         Ok(())
     }
+
+    fn change_state(
+        &mut self,
+        _app: &mut App,
+        _assets: &mut Assets,
+        _gfx: &mut Graphics,
+        _plugins: &mut Plugins,
+    ) -> Option<Box<dyn ProgramState>> {
+        if !self.reconnect {
+            return None;
+        }
+
+        let token = self.connection.session_token()?;
+        // Leaving would end the session we're about to resume
+        self.connection.abandon();
+        let server_ui = self.ecs.resources.remove::<Program>();
+
+        let connecting = Connecting::resume(
+            self.connection.ip(),
+            self.connection.port(),
+            server_ui,
+            self.connection.username(),
+            self.spectator.is_some(),
+            token,
+        );
+
+        match connecting {
+            Ok(connecting) => Some(connecting.into()),
+            Err(err) => Some(ErrorState::from(&*err).into()),
+        }
+    }
 }
 
 impl Game {
     fn accept_messages(&mut self) -> anyhow::Result<()> {
+        let mut ack = None;
+
         while let Some(message) = self.connection.receive()? {
-            if let FromServerMessage::EcsChanges(changes) = message {
-                for change in changes {
-                    self.ecs.handle_protocol(change)?;
+            match message {
+                FromServerMessage::EcsChanges(changes) => {
+                    for change in changes {
+                        self.ecs.handle_protocol(change)?;
+                    }
+                }
+                FromServerMessage::Snapshot(snapshot) => {
+                    if let Some(tick) = self.ecs.apply_snapshot(snapshot)? {
+                        ack = Some(tick);
+                    }
                 }
+                FromServerMessage::Scoreboard(scoreboard) => {
+                    self.ecs.resources.insert(scoreboard);
+                }
+                FromServerMessage::Chat(message) => self.chat.push(message),
+                FromServerMessage::OwnId(id) => {
+                    if let Some(spectator) = &mut self.spectator {
+                        spectator.joining(id);
+                    }
+                }
+                FromServerMessage::NoFreeSlot => {
+                    if let Some(spectator) = &mut self.spectator {
+                        spectator.no_free_slot();
+                    }
+                }
+                // The server forgot our session, e.g. because it restarted
+                FromServerMessage::JoinResponse(Err(rejection)) => bail!(rejection),
+                _ => {}
             }
         }
 
+        // Acknowledge only the newest snapshot, once per frame is plenty
+        if let Some(tick) = ack {
+            self.connection.send(FromClientMessage::AckSnapshot(tick))?;
+        }
+
         Ok(())
     }
 
+    /// Leaves the spectator camera behind for the player the server spawned for us
+    fn start_playing(&mut self, player: Entity) {
+        if let Some(spectator) = self.spectator.take() {
+            self.ecs.world.despawn(spectator.camera()).ok();
+        }
+
+        self.my_entity = player;
+        self.ecs.resources.insert(MyEntity(player));
+    }
+
     fn debug_ui(&mut self, ui: &mut Ui) {
         if ui.checkbox(&mut self.profiler, "Profiler").changed() {
             puffin::set_scopes_on(self.profiler);
         };
 
-        Grid::new("debug_grid_1").show(ui, |_ui| {});
+        let stats = self.connection.stats();
+        Grid::new("debug_grid_1").show(ui, |ui| {
+            ui.label("RTT");
+            ui.label(stats.ping_ms().map_or_else(|| "-".to_string(), |ping| format!("{ping} ms")));
+            ui.end_row();
+
+            ui.label("Jitter");
+            ui.label(format!("{} ms", stats.jitter.as_millis()));
+            ui.end_row();
+
+            ui.label("Packet loss");
+            ui.label(format!("{:.1}%", stats.loss * 100.0));
+            ui.end_row();
+
+            ui.label("In");
+            ui.label(format!("{:.1} kB/s", stats.bytes_in_per_sec / 1000.0));
+            ui.end_row();
+
+            ui.label("Out");
+            ui.label(format!("{:.1} kB/s", stats.bytes_out_per_sec / 1000.0));
+            ui.end_row();
+        });
     }
 }
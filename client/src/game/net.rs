@@ -1,40 +1,85 @@
-use crate::client::{Client, ClientError};
+use crate::args::ARGS;
+use crate::client::{Client, ClientError, ClientReceiver, ClientSender};
 use anyhow::anyhow;
-use common::{FromClientMessage, FromServerMessage};
+use common::net::stats::NetStatsSummary;
+use common::{FromClientMessage, FromServerMessage, SessionToken};
 use message_io::network::RemoteAddr;
 use std::net::{IpAddr, SocketAddr};
 use tokio::sync::mpsc::error::TryRecvError;
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-
-pub type ClientReceiver = UnboundedReceiver<Result<FromServerMessage, ClientError>>;
-pub type ClientSender = UnboundedSender<FromClientMessage>;
 
 pub struct Connection {
     client: Client,
     receiver: ClientReceiver,
     sender: ClientSender,
+
+    ip: IpAddr,
+    port: u16,
+    username: String,
 }
 
 impl Connection {
-    pub fn new(ip: IpAddr, port: u16, username: &str) -> anyhow::Result<Self> {
-        let addr = RemoteAddr::Socket(SocketAddr::new(ip, port));
-        let mut client = Client::new(addr)?;
-        let (receiver, sender) = client.start(username)?;
+    /// Connects to a server, resuming the given session if there is one
+    pub fn new(
+        ip: IpAddr,
+        port: u16,
+        username: &str,
+        spectate: bool,
+        resume: Option<SessionToken>,
+    ) -> anyhow::Result<Self> {
+        let addr = RemoteAddr::Socket(SocketAddr::new(ip, ARGS.transport.port(port)?));
+        let mut client = Client::new(addr, ARGS.transport)?;
+        if let Some(conditions) = ARGS.sim.conditions() {
+            client.simulate(conditions);
+        }
+        let (receiver, sender) = match resume {
+            // The server remembers whether the session was spectating
+            Some(token) => client.resume(username, token)?,
+            None if spectate => client.spectate(username)?,
+            None => client.start(username)?,
+        };
 
         Ok(Self {
             client,
             receiver,
             sender,
+            ip,
+            port,
+            username: username.to_string(),
         })
     }
 
+    pub fn ip(&self) -> IpAddr {
+        self.ip
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Drops the connection without leaving, used when handing the session over to a new one
+    pub fn abandon(&mut self) {
+        self.client.abandon();
+    }
+
+    pub fn session_token(&self) -> Option<SessionToken> {
+        self.client.session_token()
+    }
+
+    pub fn stats(&self) -> NetStatsSummary {
+        self.client.stats()
+    }
+
     pub fn receive(&mut self) -> anyhow::Result<Option<FromServerMessage>> {
         match self.receiver.try_recv() {
+            Ok(Ok(FromServerMessage::Disconnect(reason))) => Err(anyhow!(ClientError::Removed(reason))),
             Ok(Ok(message)) => Ok(Some(message)),
             Err(TryRecvError::Empty) => Ok(None),
-            Err(TryRecvError::Disconnected) | Ok(Err(ClientError::Disconnected)) => {
-                Err(anyhow!(ClientError::Disconnected))
-            }
+            Ok(Err(err)) => Err(anyhow!(err)),
+            Err(TryRecvError::Disconnected) => Err(anyhow!(ClientError::Disconnected)),
         }
     }
 
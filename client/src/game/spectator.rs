@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant};
+
+use common::defaults::PLAYER_SPEED;
+use common::ecs::components::{InputState, LookDirection, Player, Position};
+use common::ecs::system::movement;
+use common::map::Map;
+use common::UserID;
+use glam::Vec2;
+use hecs::{Entity, World};
+use notan::draw::{Draw, DrawTextSection, Font};
+use notan::prelude::Color;
+
+/// Free flying is faster than walking, there's no one to catch up with
+const FLY_SPEED: f32 = 2.0 * PLAYER_SPEED;
+/// How long the notice about the server being full stays up
+const NO_FREE_SLOT_DURATION: Duration = Duration::from_secs(3);
+
+const FONT_SIZE: f32 = 16.0;
+const LINE_HEIGHT: f32 = 20.0;
+/// Below the fps counter
+const TOP_OFFSET: f32 = 30.0;
+const LEFT_OFFSET: f32 = 10.0;
+
+/// View of someone watching the game without a player.
+/// Flies around freely or follows a player, the server never hears where the camera is.
+pub struct Spectator {
+    /// Local entity the view is rendered from, only has a position
+    camera: Entity,
+    /// Player whose view is shown, None while flying freely
+    following: Option<Entity>,
+    /// Id of the player the server spawned for us, until it gets replicated
+    joining: Option<UserID>,
+    /// When the server last turned down our request to play
+    no_free_slot: Option<Instant>,
+    font: Font,
+}
+
+impl Spectator {
+    pub fn new(world: &mut World, map: &Map, font: Font) -> Self {
+        let pos = map.random_empty_spot().unwrap_or(Position(Vec2::ONE));
+
+        Self {
+            camera: world.spawn((pos,)),
+            following: None,
+            joining: None,
+            no_free_slot: None,
+            font,
+        }
+    }
+
+    pub fn camera(&self) -> Entity {
+        self.camera
+    }
+
+    pub fn following(&self) -> Option<Entity> {
+        self.following
+    }
+
+    /// Follows the player after the current one, going back to flying freely after the last one
+    pub fn follow_next(&mut self, world: &mut World) {
+        let mut players: Vec<(UserID, Entity)> = world
+            .query_mut::<&Player>()
+            .with::<&Position>()
+            .into_iter()
+            .map(|(entity, player)| (player.id, entity))
+            .collect();
+        players.sort_unstable();
+
+        let current = self
+            .following
+            .and_then(|following| players.iter().position(|&(_, entity)| entity == following));
+
+        self.following = match current {
+            Some(index) => players.get(index + 1),
+            None => players.first(),
+        }
+        .map(|&(_, entity)| entity);
+    }
+
+    /// Moves the camera along with the followed player, or by the inputs while flying freely
+    pub fn update(&mut self, world: &mut World, map: &Map, input: &InputState, dt: f32) {
+        let target = self
+            .following
+            .and_then(|following| world.get::<&Position>(following).ok().map(|pos| pos.0));
+
+        let camera = match world.query_one_mut::<&mut Position>(self.camera) {
+            Ok(camera) => camera,
+            Err(_) => return,
+        };
+
+        match target {
+            Some(target) => camera.0 = target,
+            // Also when the followed player went away, flying on from where they were
+            None => {
+                self.following = None;
+
+                let bounds = Vec2::new(map.width as f32, map.height as f32);
+                camera.0 = (camera.0 + movement::velocity(input, FLY_SPEED) * dt).clamp(Vec2::ZERO, bounds);
+            }
+        }
+    }
+
+    /// Where the followed player is looking, None while flying freely
+    pub fn look_direction(&self, world: &World) -> Option<Vec2> {
+        let following = self.following?;
+        world.get::<&LookDirection>(following).ok().map(|dir| dir.0)
+    }
+
+    /// The server gave us a player, it's ours once it shows up
+    pub fn joining(&mut self, id: UserID) {
+        self.joining = Some(id);
+    }
+
+    pub fn no_free_slot(&mut self) {
+        self.no_free_slot = Some(Instant::now());
+    }
+
+    /// Our new player, once the server has replicated it
+    pub fn joined_player(&self, world: &mut World) -> Option<Entity> {
+        let id = self.joining?;
+        world
+            .query_mut::<&Player>()
+            .into_iter()
+            .find(|(_, player)| player.id == id)
+            .map(|(entity, _)| entity)
+    }
+
+    /// Says who's being watched and how to get into the game
+    pub fn draw(&self, draw: &mut Draw, world: &World) {
+        let watching = self
+            .following
+            .and_then(|following| world.get::<&Player>(following).ok().map(|player| player.name.clone()));
+
+        let status = match watching {
+            Some(name) => format!("Spectating {name}"),
+            None => "Spectating".to_string(),
+        };
+        self.draw_line(draw, &status, TOP_OFFSET);
+        self.draw_line(draw, "F to follow the next player, P to play", TOP_OFFSET + LINE_HEIGHT);
+
+        if self.no_free_slot.map_or(false, |since| since.elapsed() < NO_FREE_SLOT_DURATION) {
+            self.draw_line(draw, "The server is full, try again later", TOP_OFFSET + 2.0 * LINE_HEIGHT);
+        }
+    }
+
+    fn draw_line(&self, draw: &mut Draw, text: &str, y: f32) {
+        draw.text(&self.font, text)
+            .position(LEFT_OFFSET + 1.0, y + 1.0)
+            .size(FONT_SIZE)
+            .color(Color::BLACK);
+        draw.text(&self.font, text)
+            .position(LEFT_OFFSET, y)
+            .size(FONT_SIZE)
+            .color(Color::WHITE);
+    }
+}
@@ -1,14 +1,25 @@
 #![allow(dead_code)]
 
 mod args;
-mod client;
+pub mod client;
+mod discovery;
+#[cfg(feature = "graphics")]
 mod game;
+mod master;
+mod poller;
+#[cfg(feature = "graphics")]
 mod menu;
+#[cfg(feature = "graphics")]
 mod net_test;
+mod query;
+#[cfg(feature = "graphics")]
 pub mod program;
 mod helpers;
+#[cfg(feature = "graphics")]
 mod connecting;
+#[cfg(feature = "graphics")]
 mod error;
+#[cfg(feature = "graphics")]
 mod errorwindow;
 
 /// Like [puffin::profile_scope], but allows chaining multiple scopes after each other, instead of inside another.
@@ -0,0 +1,91 @@
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use common::master::{FromMasterMessage, ListedServer, ToMasterMessage};
+
+use crate::poller::UdpPoller;
+
+/// How often the list is fetched again
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Fetches the public server list from the master server
+pub struct MasterList {
+    poller: UdpPoller,
+    master: SocketAddr,
+
+    /// Page of the list we're waiting for, the next one is only asked for once it arrives
+    next_page: u32,
+    /// The master server has sent at least one page
+    answered: bool,
+    servers: Vec<ListedServer>,
+}
+
+impl MasterList {
+    /// Resolving the master server's address can block
+    pub fn new(master: &str) -> io::Result<Self> {
+        let master = master
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "master server address didn't resolve"))?;
+
+        Ok(MasterList {
+            poller: UdpPoller::new(REFRESH_INTERVAL)?,
+            master,
+            next_page: 0,
+            answered: false,
+            servers: Vec::new(),
+        })
+    }
+
+    /// None until the master server has answered
+    pub fn servers(&self) -> Option<&[ListedServer]> {
+        self.answered.then_some(self.servers.as_slice())
+    }
+
+    /// Fetches the list again on the next update
+    pub fn refresh(&mut self) {
+        self.poller.restart();
+    }
+
+    /// Requests the list again every refresh interval, one page after the other
+    pub fn update(&mut self) -> io::Result<()> {
+        if self.poller.is_due() {
+            self.request(0)?;
+        }
+
+        while let Some((data, from)) = self.poller.receive()? {
+            if from != self.master {
+                continue;
+            }
+
+            if let Ok(FromMasterMessage::ServerList { page, pages, servers }) = bincode::deserialize(data) {
+                // Duplicated or late pages of an earlier request
+                if page != self.next_page {
+                    continue;
+                }
+
+                // The first page starts a new list, the old one stays up until then
+                if page == 0 {
+                    self.answered = true;
+                    self.servers.clear();
+                }
+                self.servers.extend(servers);
+
+                if page + 1 < pages {
+                    self.request(page + 1)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn request(&mut self, page: u32) -> io::Result<()> {
+        self.next_page = page;
+
+        let data = bincode::serialize(&ToMasterMessage::List { page })
+            .expect("Master server messages should always be serializable");
+        self.poller.send(&data, self.master)
+    }
+}
@@ -1,17 +1,18 @@
 use std::{
     fmt::{Display, Formatter},
-    net::UdpSocket,
+    net::{Ipv4Addr, TcpListener, UdpSocket},
 };
 
 use admin_client::program::Program;
-use common::defaults::{IP, PORT};
+use common::net::transport::GameTransport;
+use common::defaults::{IP, MAX_USERNAME_LENGTH, PORT};
 use notan::{
     egui::{self, EguiPluginSugar},
     prelude::{App, Assets, Color, Graphics, Plugins},
 };
 
 use crate::{
-    connecting::Connecting, error::ErrorState, errorwindow::ErrorWindows,
+    args::ARGS, connecting::Connecting, error::ErrorState, errorwindow::ErrorWindows,
     program::state::ProgramState,
 };
 
@@ -62,12 +63,35 @@ impl HostingMenu {
             }
         };
 
-        if !udp_port_is_available(self.processed_port.unwrap()) {
-            self.errors.add_error("Port is already used".to_string());
-            return false;
+        // Listens on every interface so others on the LAN can find and join it
+        // UDP for everyone else, and whatever we connect to it with ourselves
+        let mut transports = vec![GameTransport::Udp];
+        if ARGS.transport != GameTransport::Udp {
+            transports.push(ARGS.transport);
+        }
+
+        // Every transport has to fit on its own port, not just the one that was typed in
+        for &transport in &transports {
+            let port = match transport.port(self.processed_port.unwrap()) {
+                Ok(port) => port,
+                Err(error) => {
+                    self.errors.add_error(error.to_string());
+                    return false;
+                }
+            };
+
+            if !port_is_available(transport, port) {
+                self.errors.add_error(format!("Port {port} is already used"));
+                return false;
+            }
         }
 
-        let mut p = Program::new(IP, self.processed_port.unwrap(), false);
+        let mut p = Program::new(
+            Ipv4Addr::UNSPECIFIED.into(),
+            self.processed_port.unwrap(),
+            transports,
+            false,
+        );
         if let Err(error) = p.run() {
             self.errors.add_error(error.to_string());
             return false;
@@ -79,8 +103,11 @@ impl HostingMenu {
     }
 }
 
-fn udp_port_is_available(port: u16) -> bool {
-    UdpSocket::bind((IP, port)).is_ok()
+fn port_is_available(transport: GameTransport, port: u16) -> bool {
+    match transport {
+        GameTransport::Udp => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok(),
+        GameTransport::Tcp | GameTransport::Ws => TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok(),
+    }
 }
 
 impl ProgramState for HostingMenu {
@@ -111,7 +138,7 @@ impl ProgramState for HostingMenu {
                     }
 
                     ui.label("Username");
-                    ui.text_edit_singleline(&mut self.username);
+                    ui.add(egui::TextEdit::singleline(&mut self.username).char_limit(MAX_USERNAME_LENGTH));
 
                     ui.add_space(10.0);
                     ui.vertical_centered(|ui| {
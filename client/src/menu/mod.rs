@@ -10,13 +10,21 @@ use crate::program::state::ProgramState;
 
 use self::hosting::HostingMenu;
 use self::quick_join::QuickJoinMenu;
+use self::server_browser::ServerBrowser;
 use self::server_selection::ServerSelectionMenu;
 use common::defaults::GAME_NAME;
 
 pub mod hosting;
 pub mod quick_join;
+pub mod server_browser;
+pub mod server_info;
 pub mod server_selection;
 
+/// Menus showing answers polled from the network draw every frame, so the answers show up without moving the mouse
+fn keep_polling(ctx: &egui::Context) {
+    ctx.request_repaint();
+}
+
 #[derive(Default)]
 pub struct Menu {
     next_state: Option<NextState>,
@@ -32,6 +40,7 @@ enum NextState {
     NetworkTest,
     HostingMenu,
     ServerSelectionMenu,
+    ServerBrowser,
     QuickJoinMenu,
 }
 
@@ -81,6 +90,16 @@ impl ProgramState for Menu {
                         })
                     });
 
+                    ui.vertical_centered(|ui| {
+                        ui.set_width(egui_center_width(ui));
+                        if ui
+                            .add_sized(double_button_size, egui::Button::new("Server Browser"))
+                            .clicked()
+                        {
+                            self.next_state = Some(NextState::ServerBrowser);
+                        }
+                    });
+
                     ui.vertical_centered(|ui| {
                         ui.set_width(egui_center_width(ui));
                         ui.horizontal(|ui| {
@@ -140,6 +159,7 @@ impl ProgramState for Menu {
             NextState::NetworkTest => Some(NetworkTest::new().into()),
             NextState::HostingMenu => Some(HostingMenu::new().into()),
             NextState::ServerSelectionMenu => Some(ServerSelectionMenu::new().into()),
+            NextState::ServerBrowser => Some(ServerBrowser::new().into()),
             NextState::QuickJoinMenu => Some(QuickJoinMenu::new().into()),
         }
     }
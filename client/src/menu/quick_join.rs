@@ -3,7 +3,7 @@ use std::{
     net::ToSocketAddrs,
 };
 
-use common::defaults::{DEFAULT_PLAYER_NAME, PORT, QUICK_JOIN_IP};
+use common::defaults::{DEFAULT_PLAYER_NAME, MAX_USERNAME_LENGTH, PORT, QUICK_JOIN_IP};
 use notan::{
     egui::{self, EguiPluginSugar},
     prelude::{App, Assets, Color, Graphics, Plugins},
@@ -11,14 +11,16 @@ use notan::{
 
 use crate::{
     connecting::Connecting, error::ErrorState, errorwindow::ErrorWindows,
-    program::state::ProgramState,
+    program::state::ProgramState, query::ServerQuery,
 };
 
-use super::Menu;
+use super::server_info::server_info_ui;
+use super::{keep_polling, Menu};
 
 enum NextState {
     Menu,
     Game,
+    Spectate,
 }
 
 #[derive(Default)]
@@ -26,6 +28,9 @@ pub struct QuickJoinMenu {
     errors: ErrorWindows,
     username: String,
     next_state: Option<NextState>,
+
+    /// None if the quick join server couldn't be resolved
+    query: Option<ServerQuery>,
 }
 
 impl Display for QuickJoinMenu {
@@ -36,10 +41,26 @@ impl Display for QuickJoinMenu {
 
 impl QuickJoinMenu {
     pub fn new() -> QuickJoinMenu {
+        let query = format!("{QUICK_JOIN_IP}:{PORT}")
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .and_then(|target| ServerQuery::new(target).ok());
+
         QuickJoinMenu {
             errors: ErrorWindows::new(),
             username: DEFAULT_PLAYER_NAME.to_string(),
             next_state: None,
+            query,
+        }
+    }
+
+    fn update_query(&mut self) {
+        if let Some(query) = &mut self.query {
+            if let Err(err) = query.update() {
+                self.errors.add_error(format!("Failed to query {}: {err}", query.target()));
+                self.query = None;
+            }
         }
     }
 
@@ -69,7 +90,11 @@ impl ProgramState for QuickJoinMenu {
         gfx: &mut Graphics,
         plugins: &mut Plugins,
     ) -> anyhow::Result<()> {
+        self.update_query();
+
         let mut output = plugins.egui(|ctx| {
+            keep_polling(ctx);
+
             egui::CentralPanel::default().show(ctx, |ui| {
                 self.errors.draw_errors(ctx);
 
@@ -80,8 +105,13 @@ impl ProgramState for QuickJoinMenu {
                     ui.label(format!("Joining server: {QUICK_JOIN_IP}:{PORT}"));
                     ui.add_space(5.0);
 
+                    if let Some(query) = &self.query {
+                        server_info_ui(ui, query);
+                        ui.add_space(5.0);
+                    }
+
                     ui.label("Username");
-                    ui.text_edit_singleline(&mut self.username);
+                    ui.add(egui::TextEdit::singleline(&mut self.username).char_limit(MAX_USERNAME_LENGTH));
 
                     ui.add_space(10.0);
                     ui.vertical_centered(|ui| {
@@ -90,6 +120,9 @@ impl ProgramState for QuickJoinMenu {
                             if ui.button("Join").clicked() && self.process_inputs() {
                                 self.next_state = Some(NextState::Game)
                             }
+                            if ui.button("Spectate").clicked() && self.process_inputs() {
+                                self.next_state = Some(NextState::Spectate)
+                            }
                             if ui.button("Back").clicked() {
                                 self.next_state = Some(NextState::Menu);
                             }
@@ -115,21 +148,26 @@ impl ProgramState for QuickJoinMenu {
         _gfx: &mut Graphics,
         _plugins: &mut Plugins,
     ) -> Option<Box<dyn ProgramState>> {
-        match self.next_state.take()? {
-            NextState::Game => {
-                let socketaddr = format!("{QUICK_JOIN_IP}:{PORT}")
-                    .to_socket_addrs()
-                    .unwrap()
-                    .next()
-                    .unwrap();
-
-                let state =
-                    Connecting::new(socketaddr.ip(), socketaddr.port(), None, &self.username)
-                        .map(|v| v.into())
-                        .unwrap_or_else(|err| ErrorState::from(&*err).into());
-                Some(state)
-            }
-            NextState::Menu => Some(Menu::new().into()),
-        }
+        let spectate = match self.next_state.take()? {
+            NextState::Game => false,
+            NextState::Spectate => true,
+            NextState::Menu => return Some(Menu::new().into()),
+        };
+
+        let socketaddr = format!("{QUICK_JOIN_IP}:{PORT}")
+            .to_socket_addrs()
+            .unwrap()
+            .next()
+            .unwrap();
+
+        let connecting = if spectate {
+            Connecting::spectate(socketaddr.ip(), socketaddr.port(), None, &self.username)
+        } else {
+            Connecting::new(socketaddr.ip(), socketaddr.port(), None, &self.username)
+        };
+        let state = connecting
+            .map(|v| v.into())
+            .unwrap_or_else(|err| ErrorState::from(&*err).into());
+        Some(state)
     }
 }
@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    net::SocketAddr,
+};
+
+use common::{
+    defaults::{DEFAULT_PLAYER_NAME, MAX_USERNAME_LENGTH},
+    handshake::PROTOCOL_VERSION,
+};
+use notan::{
+    egui::{self, EguiPluginSugar},
+    prelude::{App, Assets, Color, Graphics, Plugins},
+};
+
+use crate::{
+    args::ARGS, connecting::Connecting, error::ErrorState, errorwindow::ErrorWindows,
+    master::MasterList, program::state::ProgramState, query::ServerQuery,
+};
+
+use super::{keep_polling, Menu};
+
+enum NextState {
+    Menu,
+    Game(SocketAddr),
+}
+
+/// Lists the public servers known to the master server
+#[derive(Default)]
+pub struct ServerBrowser {
+    errors: ErrorWindows,
+    next_state: Option<NextState>,
+    username: String,
+
+    /// None if the master server couldn't be reached
+    list: Option<MasterList>,
+    /// Measures the ping of every listed server
+    queries: HashMap<SocketAddr, ServerQuery>,
+}
+
+impl Display for ServerBrowser {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ServerBrowser")
+    }
+}
+
+impl ServerBrowser {
+    pub fn new() -> ServerBrowser {
+        let mut errors = ErrorWindows::new();
+        let list = match MasterList::new(&ARGS.master) {
+            Ok(list) => Some(list),
+            Err(err) => {
+                errors.add_error(format!("Can't reach master server {}: {err}", ARGS.master));
+                None
+            }
+        };
+
+        ServerBrowser {
+            errors,
+            next_state: None,
+            username: DEFAULT_PLAYER_NAME.to_string(),
+            list,
+            queries: HashMap::new(),
+        }
+    }
+
+    fn update_list(&mut self) {
+        let list = match &mut self.list {
+            Some(list) => list,
+            None => return,
+        };
+
+        if let Err(err) = list.update() {
+            self.errors.add_error(format!("Stopped fetching the server list: {err}"));
+            self.list = None;
+            return;
+        }
+
+        let servers = list.servers().unwrap_or_default();
+        self.queries
+            .retain(|addr, _| servers.iter().any(|server| server.addr == *addr));
+        for server in servers {
+            if !self.queries.contains_key(&server.addr) {
+                if let Ok(query) = ServerQuery::new(server.addr) {
+                    self.queries.insert(server.addr, query);
+                }
+            }
+        }
+
+        // A server not answering just means it has no ping to show
+        for query in self.queries.values_mut() {
+            query.update().ok();
+        }
+    }
+
+    fn servers_ui(&mut self, ui: &mut egui::Ui) {
+        let servers = match self.list.as_ref().map(|list| list.servers()) {
+            Some(Some(servers)) => servers,
+            Some(None) => {
+                ui.label("Fetching servers...");
+                return;
+            }
+            None => return,
+        };
+
+        if servers.is_empty() {
+            ui.label("No servers are online right now");
+            return;
+        }
+
+        egui::Grid::new("server_browser").striped(true).show(ui, |ui| {
+            for header in ["Name", "Players", "Ping", ""] {
+                ui.label(header);
+            }
+            ui.end_row();
+
+            for server in servers {
+                let ping = self
+                    .queries
+                    .get(&server.addr)
+                    .and_then(|query| query.ping())
+                    .map_or_else(|| "-".to_string(), |ping| format!("{} ms", ping.as_millis()));
+                let compatible = server.protocol_version == PROTOCOL_VERSION;
+
+                ui.label(&server.name);
+                ui.label(format!("{}/{}", server.players, server.max_players));
+                ui.label(ping);
+                if ui
+                    .add_enabled(compatible, egui::Button::new("Join"))
+                    .on_disabled_hover_text("Server is running a different version")
+                    .clicked()
+                {
+                    self.next_state = Some(NextState::Game(server.addr));
+                }
+                ui.end_row();
+            }
+        });
+    }
+}
+
+impl ProgramState for ServerBrowser {
+    fn draw(
+        &mut self,
+        _app: &mut App,
+        _assets: &mut Assets,
+        gfx: &mut Graphics,
+        plugins: &mut Plugins,
+    ) -> anyhow::Result<()> {
+        self.update_list();
+
+        let mut output = plugins.egui(|ctx| {
+            keep_polling(ctx);
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                self.errors.draw_errors(ctx);
+
+                ui.vertical_centered(|ui| {
+                    ui.heading("Server Browser");
+                    ui.add_space(10.0);
+
+                    ui.label("Username");
+                    ui.add(egui::TextEdit::singleline(&mut self.username).char_limit(MAX_USERNAME_LENGTH));
+                    ui.add_space(10.0);
+
+                    self.servers_ui(ui);
+
+                    ui.add_space(10.0);
+                    ui.vertical_centered(|ui| {
+                        ui.set_width(ui.available_width() / 4.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Refresh").clicked() {
+                                if let Some(list) = &mut self.list {
+                                    list.refresh();
+                                }
+                            }
+                            if ui.button("Back").clicked() {
+                                self.next_state = Some(NextState::Menu);
+                            }
+                        })
+                    });
+                });
+            });
+        });
+
+        output.clear_color(Color::BLACK);
+
+        if output.needs_repaint() {
+            gfx.render(&output);
+        }
+
+        Ok(())
+    }
+
+    fn change_state(
+        &mut self,
+        _app: &mut App,
+        _assets: &mut Assets,
+        _gfx: &mut Graphics,
+        _plugins: &mut Plugins,
+    ) -> Option<Box<dyn ProgramState>> {
+        match self.next_state.take()? {
+            NextState::Game(addr) => {
+                let state = Connecting::new(addr.ip(), addr.port(), None, &self.username)
+                    .map(|v| v.into())
+                    .unwrap_or_else(|err| ErrorState::from(&*err).into());
+                Some(state)
+            }
+            NextState::Menu => Some(Menu::new().into()),
+        }
+    }
+}
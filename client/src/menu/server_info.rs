@@ -0,0 +1,52 @@
+use common::handshake::PROTOCOL_VERSION;
+use notan::egui::{self, Ui};
+
+use crate::query::ServerQuery;
+
+/// Shows what a server answered to a query, so players know what they're joining
+pub fn server_info_ui(ui: &mut Ui, query: &ServerQuery) {
+    let info = match query.info() {
+        Some(info) => info,
+        None => {
+            ui.label(format!("Waiting for {} to answer...", query.target()));
+            return;
+        }
+    };
+
+    ui.strong(&info.name);
+    if info.protocol_version != PROTOCOL_VERSION {
+        ui.colored_label(
+            egui::Color32::RED,
+            "Server is running a different version, you won't be able to join",
+        );
+    }
+
+    egui::Grid::new("server_info").show(ui, |ui| {
+        ui.label("Mode");
+        ui.label(info.mode.to_string());
+        ui.end_row();
+
+        ui.label("Map");
+        ui.label(format!("{}x{}", info.map_width, info.map_height));
+        ui.end_row();
+
+        ui.label("Players");
+        ui.label(format!("{}/{}", info.players.len(), info.max_players));
+        ui.end_row();
+
+        ui.label("Ping");
+        ui.label(match query.ping() {
+            Some(ping) => format!("{} ms", ping.as_millis()),
+            None => "-".to_string(),
+        });
+        ui.end_row();
+    });
+
+    if !info.players.is_empty() {
+        ui.collapsing("Player list", |ui| {
+            for player in &info.players {
+                ui.label(player);
+            }
+        });
+    }
+}
@@ -3,22 +3,24 @@ use std::{
     net::{SocketAddr, ToSocketAddrs},
 };
 
-use common::defaults::{IP, PORT};
+use common::defaults::{IP, MAX_USERNAME_LENGTH, PORT};
 use notan::{
     egui::{self, EguiPluginSugar},
     prelude::{App, Assets, Color, Graphics, Plugins},
 };
 
 use crate::{
-    connecting::Connecting, error::ErrorState, errorwindow::ErrorWindows,
-    program::state::ProgramState,
+    connecting::Connecting, discovery::LanDiscovery, error::ErrorState,
+    errorwindow::ErrorWindows, program::state::ProgramState, query::ServerQuery,
 };
 
-use super::Menu;
+use super::server_info::server_info_ui;
+use super::{keep_polling, Menu};
 
 enum NextState {
     Menu,
     Game,
+    Spectate,
 }
 
 #[derive(Default)]
@@ -29,6 +31,12 @@ pub struct ServerSelectionMenu {
     username: String,
 
     processed_ip: Option<SocketAddr>,
+
+    /// None if looking for LAN servers failed
+    discovery: Option<LanDiscovery>,
+
+    /// Details of the server whose address is typed in, None while the address doesn't resolve
+    query: Option<ServerQuery>,
 }
 
 impl Display for ServerSelectionMenu {
@@ -39,13 +47,77 @@ impl Display for ServerSelectionMenu {
 
 impl ServerSelectionMenu {
     pub fn new() -> ServerSelectionMenu {
-        ServerSelectionMenu {
+        let mut errors = ErrorWindows::new();
+        let discovery = match LanDiscovery::new() {
+            Ok(discovery) => Some(discovery),
+            Err(err) => {
+                errors.add_error(format!("Can't look for LAN servers: {err}"));
+                None
+            }
+        };
+
+        let mut menu = ServerSelectionMenu {
             next_state: None,
             ip: format!("{IP}:{PORT}"),
             username: String::new(),
 
             processed_ip: None,
-            errors: ErrorWindows::new(),
+            errors,
+            discovery,
+            query: None,
+        };
+        menu.update_query_target();
+        menu
+    }
+
+    /// Starts querying the typed in address, resolving it can block so this isn't done on every keystroke
+    fn update_query_target(&mut self) {
+        let target = self.ip.to_socket_addrs().ok().and_then(|mut addrs| addrs.next());
+        self.query = target.and_then(|target| ServerQuery::new(target).ok());
+    }
+
+    fn update_query(&mut self) {
+        if let Some(query) = &mut self.query {
+            if let Err(err) = query.update() {
+                self.errors.add_error(format!("Failed to query {}: {err}", query.target()));
+                self.query = None;
+            }
+        }
+    }
+
+    fn update_discovery(&mut self) {
+        if let Some(discovery) = &mut self.discovery {
+            if let Err(err) = discovery.update() {
+                self.errors.add_error(format!("Stopped looking for LAN servers: {err}"));
+                self.discovery = None;
+            }
+        }
+    }
+
+    fn lan_servers_ui(&mut self, ui: &mut egui::Ui) {
+        let discovery = match &self.discovery {
+            Some(discovery) => discovery,
+            None => return,
+        };
+
+        ui.add_space(20.0);
+        ui.heading("LAN Servers");
+        ui.add_space(5.0);
+
+        let servers = discovery.servers();
+        if servers.is_empty() {
+            ui.label("Looking for servers...");
+        }
+
+        for server in servers {
+            let label = format!(
+                "{} ({}/{}) - {}",
+                server.info.name, server.info.players, server.info.max_players, server.addr
+            );
+            if ui.button(label).clicked() {
+                self.processed_ip = Some(server.addr);
+                self.next_state = Some(NextState::Game);
+            }
         }
     }
 
@@ -70,7 +142,12 @@ impl ProgramState for ServerSelectionMenu {
         gfx: &mut Graphics,
         plugins: &mut Plugins,
     ) -> anyhow::Result<()> {
+        self.update_discovery();
+        self.update_query();
+
         let mut output = plugins.egui(|ctx| {
+            keep_polling(ctx);
+
             egui::CentralPanel::default().show(ctx, |ui| {
                 self.errors.draw_errors(ctx);
 
@@ -79,10 +156,22 @@ impl ProgramState for ServerSelectionMenu {
                     ui.add_space(10.0);
 
                     ui.label("IP & Port:");
-                    ui.text_edit_singleline(&mut self.ip);
+                    let response = ui.text_edit_singleline(&mut self.ip);
+                    if response.changed() {
+                        self.query = None;
+                    }
+                    if response.lost_focus() {
+                        self.update_query_target();
+                    }
+
+                    if let Some(query) = &self.query {
+                        ui.add_space(5.0);
+                        server_info_ui(ui, query);
+                        ui.add_space(5.0);
+                    }
 
                     ui.label("Username");
-                    ui.text_edit_singleline(&mut self.username);
+                    ui.add(egui::TextEdit::singleline(&mut self.username).char_limit(MAX_USERNAME_LENGTH));
 
                     ui.add_space(10.0);
                     ui.vertical_centered(|ui| {
@@ -95,11 +184,20 @@ impl ProgramState for ServerSelectionMenu {
 
                                 self.next_state = Some(NextState::Game);
                             }
+                            if ui.button("Spectate").clicked() {
+                                if !self.process_inputs() {
+                                    return;
+                                }
+
+                                self.next_state = Some(NextState::Spectate);
+                            }
                             if ui.button("Back").clicked() {
                                 self.next_state = Some(NextState::Menu);
                             }
                         })
                     });
+
+                    self.lan_servers_ui(ui);
                 });
             });
         });
@@ -120,15 +218,21 @@ impl ProgramState for ServerSelectionMenu {
         _gfx: &mut Graphics,
         _plugins: &mut Plugins,
     ) -> Option<Box<dyn ProgramState>> {
-        match self.next_state.take()? {
-            NextState::Game => {
-                let processed = self.processed_ip.unwrap();
-                let state = Connecting::new(processed.ip(), processed.port(), None, &self.username)
-                    .map(|v| v.into())
-                    .unwrap_or_else(|err| ErrorState::from(&*err).into());
-                Some(state)
-            }
-            NextState::Menu => Some(Menu::new().into()),
-        }
+        let spectate = match self.next_state.take()? {
+            NextState::Game => false,
+            NextState::Spectate => true,
+            NextState::Menu => return Some(Menu::new().into()),
+        };
+
+        let processed = self.processed_ip.unwrap();
+        let connecting = if spectate {
+            Connecting::spectate(processed.ip(), processed.port(), None, &self.username)
+        } else {
+            Connecting::new(processed.ip(), processed.port(), None, &self.username)
+        };
+        let state = connecting
+            .map(|v| v.into())
+            .unwrap_or_else(|err| ErrorState::from(&*err).into());
+        Some(state)
     }
 }
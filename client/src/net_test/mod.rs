@@ -10,11 +10,13 @@ use std::net::ToSocketAddrs;
 use tokio::sync::mpsc::error::TryRecvError;
 use tracing::error;
 
+use common::handshake::JoinRequest;
 use common::FromClientMessage;
 
+use crate::args::ARGS;
 use crate::client::Client;
 use crate::errorwindow::ErrorWindows;
-use crate::game::net::{ClientReceiver, ClientSender};
+use crate::client::{ClientReceiver, ClientSender};
 use crate::program::state::ProgramState;
 use common::defaults::{DEFAULT_PLAYER_NAME, IP, PORT};
 
@@ -67,8 +69,8 @@ struct Connection {
 
 impl Connection {
     fn new(ip: IpAddr, port: u16, username: &str) -> anyhow::Result<Self> {
-        let addr = RemoteAddr::Socket(SocketAddr::new(ip, port));
-        let mut client = Client::new(addr)?;
+        let addr = RemoteAddr::Socket(SocketAddr::new(ip, ARGS.transport.port(port)?));
+        let mut client = Client::new(addr, ARGS.transport)?;
         let (receiver, sender) = client.start(username)?;
 
         Ok(Self {
@@ -193,7 +195,7 @@ impl SenderWidget {
 
         if ui.button("Join").clicked() {
             self.sender
-                .send(FromClientMessage::Join(self.username.to_string()))?
+                .send(FromClientMessage::Join(JoinRequest::new(&self.username)))?
         }
 
         if ui.button("Leave").clicked() {
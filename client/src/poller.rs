@@ -0,0 +1,73 @@
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Biggest datagram any of the pollers expect an answer in
+const BUFFER_SIZE: usize = 2048;
+
+/// Non-blocking UDP socket that sends a request every so often and collects the answers.
+/// The server list, LAN discovery and server queries all run on one, polled every frame from the menus.
+pub struct UdpPoller {
+    socket: UdpSocket,
+    interval: Duration,
+    last_request: Option<Instant>,
+    buffer: [u8; BUFFER_SIZE],
+}
+
+impl UdpPoller {
+    pub fn new(interval: Duration) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_nonblocking(true)?;
+
+        Ok(UdpPoller {
+            socket,
+            interval,
+            last_request: None,
+            buffer: [0; BUFFER_SIZE],
+        })
+    }
+
+    /// Same as new, but allowed to send to broadcast addresses
+    pub fn broadcast(interval: Duration) -> io::Result<Self> {
+        let poller = Self::new(interval)?;
+        poller.socket.set_broadcast(true)?;
+        Ok(poller)
+    }
+
+    /// True if the interval has passed since the last request, or nothing has been sent yet
+    pub fn is_due(&self) -> bool {
+        self.last_request.map_or(true, |last| last.elapsed() >= self.interval)
+    }
+
+    /// When the last request was sent
+    pub fn sent_at(&self) -> Option<Instant> {
+        self.last_request
+    }
+
+    /// Makes the next update send a request right away
+    pub fn restart(&mut self) {
+        self.last_request = None;
+    }
+
+    /// Sends a request and restarts the interval.
+    /// A target that isn't up isn't an error, it just never answers.
+    pub fn send<A: ToSocketAddrs>(&mut self, data: &[u8], to: A) -> io::Result<()> {
+        self.last_request = Some(Instant::now());
+
+        match self.socket.send_to(data, to) {
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// Next datagram waiting on the socket, None once all of them have been read
+    pub fn receive(&mut self) -> io::Result<Option<(&[u8], SocketAddr)>> {
+        match self.socket.recv_from(&mut self.buffer) {
+            Ok((len, from)) => Ok(Some((&self.buffer[..len], from))),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(None),
+            // An earlier request couldn't be delivered, which is an answer of its own
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use common::discovery::ServerInfo;
+use common::net::fragment::Reassembler;
+use common::net::{codec, compression, Packet};
+use common::{ClientMessage, FromClientMessage, FromServerMessage};
+
+use crate::poller::UdpPoller;
+
+/// How often a server is asked about itself again, which also refreshes the ping
+const QUERY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Asks a server about itself without joining it
+pub struct ServerQuery {
+    poller: UdpPoller,
+    target: SocketAddr,
+    reassembler: Reassembler,
+
+    info: Option<ServerInfo>,
+    ping: Option<Duration>,
+}
+
+impl ServerQuery {
+    pub fn new(target: SocketAddr) -> io::Result<Self> {
+        Ok(ServerQuery {
+            poller: UdpPoller::new(QUERY_INTERVAL)?,
+            target,
+            reassembler: Reassembler::default(),
+            info: None,
+            ping: None,
+        })
+    }
+
+    pub fn target(&self) -> SocketAddr {
+        self.target
+    }
+
+    /// Latest answer, None until the server has answered
+    pub fn info(&self) -> Option<&ServerInfo> {
+        self.info.as_ref()
+    }
+
+    /// Round trip time of the latest answered query
+    pub fn ping(&self) -> Option<Duration> {
+        self.ping
+    }
+
+    /// Queries the server every query interval, the ping is measured from the latest query
+    pub fn update(&mut self) -> io::Result<()> {
+        if self.poller.is_due() {
+            self.query()?;
+        }
+
+        let sent_at = self.poller.sent_at();
+        while let Some((data, from)) = self.poller.receive()? {
+            if from != self.target {
+                continue;
+            }
+
+            let packet: Packet = match codec::decode(data) {
+                Ok(packet) => packet,
+                Err(_) => continue,
+            };
+
+            if let Some(data) = self.reassembler.receive(packet).and_then(|data| compression::unpack(&data).ok()) {
+                if let Ok(FromServerMessage::ServerInfo(info)) = codec::decode(&data) {
+                    self.ping = sent_at.map(|sent_at| sent_at.elapsed());
+                    self.info = Some(info);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn query(&mut self) -> io::Result<()> {
+        // Not part of any session, so there's nothing to stamp it with
+        let message = ClientMessage {
+            token: None,
+            sequence: 0,
+            message: FromClientMessage::Query,
+        };
+        let data = codec::encode(&message);
+        self.poller.send(&data, self.target)
+    }
+}
@@ -1,12 +1,19 @@
 use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
 use crate::map::Textured;
 
 pub const QUICK_JOIN_IP: &str = "fps.catnip.ee";
+/// Master server keeping the public server list
+pub const MASTER_SERVER: &str = "fps.catnip.ee:1339";
+pub const MASTER_SERVER_PORT: u16 = 1339;
 
 pub const GAME_NAME: &str = "Multiplayer FPS";
 
 pub const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
 pub const PORT: u16 = 1337;
+/// Servers listen for LAN discovery probes on this port
+pub const DISCOVERY_PORT: u16 = 1338;
+pub const DEFAULT_SERVER_NAME: &str = "Multiplayer FPS server";
 
 pub const MAP_WIDTH: usize = 13; // Must be odd
 pub const MAP_HEIGHT: usize = MAP_WIDTH; // Must be odd
@@ -23,8 +30,18 @@ pub const MINIMAP_SCALE: f32 = 2.0;
 pub const PLAYER_MAX_HP: f32 = 100.0;
 pub const DEFAULT_PLAYER_HP: f32 = PLAYER_MAX_HP;
 pub const DEFAULT_PLAYER_NAME: &str = "Player";
-pub const PLAYER_SPEED: f32 = 0.1;
+pub const MAX_USERNAME_LENGTH: usize = 24;
+/// Longest chat message in characters, the server refuses longer ones
+pub const MAX_CHAT_LENGTH: usize = 200;
+pub const PLAYER_SPEED: f32 = 2.5;
 pub const PLAYER_SIZE: f32 = 0.25;
 pub const WEAPON_CRATES_AMOUNT: u32 = 5;
+pub const MAX_PLAYERS: usize = 16;
+/// Spectators don't take up a player slot, but still cost bandwidth
+pub const MAX_SPECTATORS: usize = 8;
 
 pub const TICKS_PER_SECOND: u64 = 144;
+/// Seconds of movement every input stands for, the same on the server and in prediction
+pub const TICK_DURATION: f32 = 1.0 / TICKS_PER_SECOND as f32;
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const RESUME_GRACE_PERIOD: Duration = Duration::from_secs(30);
@@ -0,0 +1,50 @@
+// Finding servers on the local network, done over UDP broadcast outside of any session
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Broadcast by clients looking for servers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiscoveryProbe {
+    /// Servers running another version don't answer, the client couldn't join them anyway
+    pub protocol_version: u32,
+}
+
+/// Sent back to the address the probe came from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiscoveryResponse {
+    pub name: String,
+    pub players: usize,
+    pub max_players: usize,
+    /// Port the game itself runs on, the IP is the one the response came from
+    pub port: u16,
+}
+
+/// Rules the server plays by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Everyone against everyone, scored by kills
+    Deathmatch,
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameMode::Deathmatch => write!(f, "Deathmatch"),
+        }
+    }
+}
+
+/// Answer to [FromClientMessage::Query](crate::FromClientMessage::Query), describes the server to someone who hasn't joined
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub name: String,
+    pub protocol_version: u32,
+    pub map_width: usize,
+    pub map_height: usize,
+    pub mode: GameMode,
+    pub max_players: usize,
+    /// Names of everyone currently playing
+    pub players: Vec<String>,
+}
@@ -3,6 +3,7 @@
 use std::num::NonZeroU64;
 
 use crate::gun::Gun;
+use crate::net::Sequence;
 use glam::Vec2;
 use serde::{Deserialize, Serialize};
 
@@ -10,7 +11,7 @@ use crate::{bulk_attribute, register_shared_components, UserID};
 
 /// Describes the instructions the server can give to the client ECS.
 /// The contained NonZeroU64 is the entity ID.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EcsProtocol {
     Insert((NonZeroU64, InsertComponent)),
     Remove((NonZeroU64, RemoveComponent)),
@@ -20,24 +21,25 @@ pub enum EcsProtocol {
 // Create InsertComponent and RemoveComponent enums with this macro
 // This is only necessary for the components that get sent over the network
 register_shared_components! {
-    Position,
-    Velocity,
-    LookDirection,
-    Size,
-    Health,
-    HeldWeapon,
-    Kills,
-    Deaths,
-    Player,
-    Bullet,
-    WeaponCrate,
-    DeadPlayer,
+    Position { 0 as position },
+    Velocity { 0 as position },
+    LookDirection { 0 as direction },
+    Size { 0 as position },
+    Health { 0 },
+    HeldWeapon { gun, ammo },
+    Kills { 0 },
+    Deaths { 0 },
+    Player { id, name },
+    Bullet { owner, gun },
+    WeaponCrate { 0 },
+    DeadPlayer {},
+    LastInput { 0 },
 }
 
 // This macro simply adds derives for all these structs.
 // You can just as easily define structs outside it and derive stuff manually
 bulk_attribute! {
-    derive(Debug, Clone, Copy, Serialize, Deserialize);
+    derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize);
     pub struct Position (pub Vec2);
     pub struct Velocity (pub Vec2);
     pub struct LookDirection (pub Vec2);
@@ -57,9 +59,13 @@ bulk_attribute! {
     pub struct WeaponCrate (pub Gun);
 
     pub struct DeadPlayer;
+
+    /// Sequence number of the last input the server applied to this player.
+    /// The client uses it to know which of its predicted inputs still need replaying.
+    pub struct LastInput(pub Sequence);
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Player {
     pub id: UserID,
     pub name: String,
@@ -98,3 +104,10 @@ pub struct InputState {
 
     pub shoot: bool,
 }
+
+impl InputState {
+    pub fn is_moving(&self) -> bool {
+        self.forward || self.backward || self.left || self.right
+    }
+}
+
@@ -22,20 +22,34 @@
 ///     // And so on...
 /// }
 /// ```
+///
+/// It also implements [WireFormat](crate::net::codec::WireFormat) for both enums and every component.
+/// A component's position in the list is its tag on the wire, so new components have to be added to the end.
+///
+/// Each component lists its fields in the order they're written, tuple structs by index.
+/// A field is encoded with its own WireFormat, unless `as position` or `as direction` quantizes it.
+/// Fields can be added to the end of a component without breaking older builds, they ignore what they don't read.
+/// ```ignore
+/// register_shared_components! {
+///     Position { 0 as position },
+///     Player { id, name },
+///     DeadPlayer {},
+/// }
+/// ```
 #[macro_export]
 macro_rules! register_shared_components {
-    ($($name:ident),+$(,)?) => {
-        use $crate::{insert_impls, remove_impls};
+    ($($name:ident { $($field:tt $(as $quantization:ident)?),* $(,)? }),+ $(,)?) => {
+        use $crate::{insert_impls, remove_impls, wire_field};
 
         // Create enums
         /// Represents inserting a single component into a world
-        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, derive_more::From)]
+        #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, derive_more::From)]
         pub enum InsertComponent {
             $($name($name),)+
         }
 
         /// Represents removing a single component from the world
-        #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
         pub enum RemoveComponent {
             $($name,)+
         }
@@ -51,6 +65,16 @@ macro_rules! register_shared_components {
                 }
             }
 
+            /// Get the [RemoveComponent] matching the type of this component.
+            /// Also useful as a key for telling component types apart.
+            pub fn to_remove(&self) -> RemoveComponent {
+                match self {
+                    $(
+                        InsertComponent::$name(_) => RemoveComponent::$name,
+                    )+
+                }
+            }
+
             pub fn query_all(world: &mut hecs::World) -> Vec<EcsProtocol> {
                 let mut vec = Vec::new();
 
@@ -63,6 +87,74 @@ macro_rules! register_shared_components {
 
                 vec
             }
+
+            /// Get a sequence of [EcsProtocol] messages that recreate a single entity as it is now
+            pub fn query_entity(world: &hecs::World, entity: hecs::Entity) -> Vec<EcsProtocol> {
+                let mut vec = Vec::new();
+
+                $(if let Ok(component) = world.get::<&$name>(entity) {
+                    vec.push(EcsProtocol::Insert((entity.to_bits(), InsertComponent::from((*component).clone()))));
+                })+
+
+                vec
+            }
+        }
+
+        // Wire format of the components themselves, fields are written in the order they're listed
+        $(impl $crate::net::codec::WireFormat for $name {
+            #[allow(unused_variables)]
+            fn encode(&self, writer: &mut $crate::net::codec::BitWriter) {
+                $(wire_field!(encode writer, self.$field $(, $quantization)?);)*
+            }
+
+            #[allow(unused_variables)]
+            fn decode(reader: &mut $crate::net::codec::BitReader) -> $crate::net::codec::Result<Self> {
+                Ok($name {
+                    $($field: wire_field!(decode reader $(, $quantization)?),)*
+                })
+            }
+        })+
+
+        // Wire format, the tag of each component is its position in the list
+        impl $crate::net::codec::WireFormat for InsertComponent {
+            fn encode(&self, writer: &mut $crate::net::codec::BitWriter) {
+                writer.write_varint(self.to_remove() as u64);
+                writer.write_section(|writer| match self {
+                    $(
+                        InsertComponent::$name(component) => $crate::net::codec::WireFormat::encode(component, writer),
+                    )+
+                });
+            }
+
+            fn decode(reader: &mut $crate::net::codec::BitReader) -> $crate::net::codec::Result<Self> {
+                let tag = reader.read_varint()?;
+                // Skipped even if the tag is unknown, so the rest of the message can still be read
+                let section = &mut reader.read_section()?;
+
+                $(
+                    if tag == RemoveComponent::$name as u64 {
+                        return Ok(InsertComponent::$name($crate::net::codec::WireFormat::decode(section)?));
+                    }
+                )+
+                Err($crate::net::codec::CodecError::UnknownTag(tag))
+            }
+        }
+
+        impl $crate::net::codec::WireFormat for RemoveComponent {
+            fn encode(&self, writer: &mut $crate::net::codec::BitWriter) {
+                writer.write_varint(*self as u64);
+            }
+
+            fn decode(reader: &mut $crate::net::codec::BitReader) -> $crate::net::codec::Result<Self> {
+                let tag = reader.read_varint()?;
+
+                $(
+                    if tag == RemoveComponent::$name as u64 {
+                        return Ok(RemoveComponent::$name);
+                    }
+                )+
+                Err($crate::net::codec::CodecError::UnknownTag(tag))
+            }
         }
 
         impl RemoveComponent {
@@ -105,6 +197,29 @@ macro_rules! register_shared_components {
     };
 }
 
+/// Writes or reads a single component field for [register_shared_components]
+#[macro_export]
+macro_rules! wire_field {
+    (encode $writer:ident, $value:expr) => {
+        $crate::net::codec::WireFormat::encode(&$value, $writer)
+    };
+    (encode $writer:ident, $value:expr, position) => {
+        $writer.write_position($value)
+    };
+    (encode $writer:ident, $value:expr, direction) => {
+        $writer.write_direction($value)
+    };
+    (decode $reader:ident) => {
+        $crate::net::codec::WireFormat::decode($reader)?
+    };
+    (decode $reader:ident, position) => {
+        $reader.read_position()?
+    };
+    (decode $reader:ident, direction) => {
+        $reader.read_direction()?
+    };
+}
+
 #[macro_export]
 macro_rules! insert_impls {
     ( $( $name:ident )+ ) => {
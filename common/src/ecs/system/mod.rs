@@ -1 +1,3 @@
 // This module is for shared server/client systems, although its possible we won't have any
+pub mod collision;
+pub mod movement;
@@ -0,0 +1,49 @@
+// Movement rules shared by the server simulation and client-side prediction
+
+use glam::Vec2;
+
+use crate::defaults::{PLAYER_SIZE, TICK_DURATION};
+use crate::ecs::components::InputState;
+use crate::ecs::system::collision::collide_with_walls;
+use crate::map::Map;
+
+/// Direction the player is looking at according to their input
+pub fn look_direction(input: &InputState) -> Vec2 {
+    Vec2::from_angle(input.look_angle)
+}
+
+/// Velocity a player with the given speed gets from their input
+pub fn velocity(input: &InputState, speed: f32) -> Vec2 {
+    let mut move_dir = Vec2::ZERO;
+
+    let forward = look_direction(input);
+    let right = forward.perp();
+
+    if input.forward {
+        move_dir += forward;
+    }
+
+    if input.backward {
+        move_dir -= forward;
+    }
+
+    if input.right {
+        move_dir += right;
+    }
+
+    if input.left {
+        move_dir -= right;
+    }
+
+    // this makes backend and frontend crash, so I commented it out for now -Jacob
+    move_dir = move_dir.normalize_or_zero();
+
+    move_dir * speed
+}
+
+/// Moves a player by a single input, which always stands for one tick.
+/// The server applies inputs with this and the client predicts with it, so both end up in the same place.
+pub fn step(map: &Map, pos: Vec2, input: &InputState, speed: f32) -> Vec2 {
+    let pos = pos + velocity(input, speed) * TICK_DURATION;
+    collide_with_walls(map, pos, PLAYER_SIZE)
+}
@@ -5,7 +5,7 @@ use rand::{distributions::Standard, prelude::Distribution, Rng};
 
 use crate::ecs::components::HeldWeapon;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Gun {
     Pistol,
     Sniper,
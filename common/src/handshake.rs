@@ -0,0 +1,75 @@
+// Version negotiation done when joining a server
+
+use std::error::Error;
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Bump this whenever [FromClientMessage](crate::FromClientMessage) or [FromServerMessage](crate::FromServerMessage)
+/// change in a way older builds can't understand.
+/// That includes how they're framed, like the [compression](crate::net::compression) marker in front of server messages.
+pub const PROTOCOL_VERSION: u32 = 6;
+
+/// Human readable build of this binary, shown when versions don't match
+pub const BUILD: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JoinRequest {
+    pub protocol_version: u32,
+    pub build: String,
+    pub username: String,
+    /// Join without a player, only watching the game
+    pub spectate: bool,
+}
+
+impl JoinRequest {
+    /// A join request for the protocol version of this build
+    pub fn new(username: &str) -> Self {
+        JoinRequest {
+            protocol_version: PROTOCOL_VERSION,
+            build: BUILD.to_string(),
+            username: username.to_string(),
+            spectate: false,
+        }
+    }
+
+    /// Same as [JoinRequest::new] but joins as a spectator
+    pub fn spectator(username: &str) -> Self {
+        JoinRequest {
+            spectate: true,
+            ..JoinRequest::new(username)
+        }
+    }
+}
+
+/// Reasons the server can refuse a [JoinRequest]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JoinRejection {
+    VersionMismatch {
+        server_version: u32,
+        server_build: String,
+    },
+    /// The session to resume doesn't exist anymore, a regular join is needed
+    SessionExpired,
+    /// Why the username isn't allowed
+    InvalidUsername(String),
+}
+
+impl Display for JoinRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JoinRejection::VersionMismatch {
+                server_version,
+                server_build,
+            } => write!(
+                f,
+                "Server is running version {server_version} (build {server_build}), \
+                but you have version {PROTOCOL_VERSION} (build {BUILD})"
+            ),
+            JoinRejection::SessionExpired => write!(f, "Your previous session has expired"),
+            JoinRejection::InvalidUsername(reason) => write!(f, "Invalid username: {reason}"),
+        }
+    }
+}
+
+impl Error for JoinRejection {}
@@ -1,31 +1,133 @@
+use discovery::ServerInfo;
 use ecs::components::{EcsProtocol, InputState};
+use handshake::{JoinRejection, JoinRequest};
 use map::Map;
+use net::Sequence;
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use snapshot::Snapshot;
 
 pub mod defaults;
+pub mod discovery;
 pub mod ecs;
 pub mod map;
+pub mod master;
 pub mod gun;
+pub mod handshake;
+pub mod net;
+pub mod snapshot;
 mod maze;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum FromClientMessage {
     Ping,
     Leave,
-    Join(String),
-    UpdateInputs(InputState)
+    Join(JoinRequest),
+    /// Reattach to the player of the session token this message carries, after losing connection
+    Resume(JoinRequest),
+    /// Input state along with a sequence number the server echoes back through [LastInput](ecs::components::LastInput),
+    /// and the tick remote players were rendered at when it was made, which hits are checked against
+    UpdateInputs(Sequence, InputState, Tick),
+    AckSnapshot(Tick),
+    /// Acknowledges a packet received on the reliable channel
+    Ack(Sequence),
+    /// Asks for [FromServerMessage::ServerInfo] without joining, the server keeps no state for it
+    Query,
+    /// Text to send to every player
+    Chat(String),
+    /// A spectator asks to spawn as a player, answered with [FromServerMessage::OwnId] or [FromServerMessage::NoFreeSlot]
+    Play,
+}
+
+/// Wraps every [FromClientMessage] so the server can tell the client apart from someone spoofing its address
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClientMessage {
+    /// Token received in [FromServerMessage::JoinResponse], None before joining
+    pub token: Option<SessionToken>,
+    /// Increases with every message so the server can measure loss
+    pub sequence: Sequence,
+    pub message: FromClientMessage,
 }
 
 pub type UserID = u64;
 
+/// Secret the server hands out on join, required on every message after that
+pub type SessionToken = u64;
+
+/// Number of ticks the server has simulated, used to stamp snapshots
+pub type Tick = u64;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum FromServerMessage {
+    /// Answer to [FromClientMessage::Join].
+    /// Its wire tag has to stay 0, so clients of any version can decode a rejection
+    JoinResponse(Result<SessionToken, JoinRejection>),
     OwnId(UserID),
     SendMap(Map),
     Pong,
-    EcsChanges(Vec<EcsProtocol>)
+    EcsChanges(Vec<EcsProtocol>),
+    Snapshot(Snapshot),
+    /// The server removed us, we shouldn't expect any more messages
+    Disconnect(DisconnectReason),
+    /// Sent periodically, since players out of sight aren't replicated
+    Scoreboard(Scoreboard),
+    ServerInfo(ServerInfo),
+    /// A player said something
+    Chat(ChatMessage),
+    /// Sent instead of [FromServerMessage::OwnId] when joining as a spectator
+    Spectating,
+    /// Answer to [FromClientMessage::Play] when the server already has as many players as it allows
+    NoFreeSlot,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scoreboard(pub Vec<ScoreboardEntry>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreboardEntry {
+    pub id: UserID,
+    pub name: String,
+    pub kills: i32,
+    pub deaths: i32,
+    /// Round trip time the server measured, None until it has a sample
+    pub ping: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub id: UserID,
+    pub name: String,
+    pub text: String,
+}
+
+/// Why the server ended a client's session
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum DisconnectReason {
+    Kicked(String),
+    Banned(String),
+    Shutdown,
+    ServerFull,
+}
+
+impl Display for DisconnectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisconnectReason::Kicked(reason) if reason.is_empty() => write!(f, "Kicked from the server"),
+            DisconnectReason::Kicked(reason) => write!(f, "Kicked from the server: {reason}"),
+            DisconnectReason::Banned(reason) if reason.is_empty() => write!(f, "Banned from the server"),
+            DisconnectReason::Banned(reason) => write!(f, "Banned from the server: {reason}"),
+            DisconnectReason::Shutdown => write!(f, "Server shut down"),
+            DisconnectReason::ServerFull => write!(f, "Server is full"),
+        }
+    }
 }
 
 pub enum Signal {
-    Tick
+    Tick,
+    /// Disconnect the player with this name
+    Kick { name: String, reason: String },
+    /// Disconnect the player with this name and refuse their address from now on
+    Ban { name: String, reason: String },
+    /// Disconnect everyone and stop the server
+    Shutdown,
 }
@@ -1,4 +1,4 @@
-use glam::Vec2;
+use glam::{IVec2, Vec2};
 use rand::{thread_rng, seq::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 
@@ -160,6 +160,45 @@ impl Map {
 
         available_coords.choose(&mut thread_rng()).copied()
     }
+
+    /// Whether a straight line between two points only crosses empty cells.
+    /// Walks the grid cell by cell along the line, so no wall is ever skipped.
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let delta = to - from;
+        let mut cell = from.floor().as_ivec2();
+        let end = to.floor().as_ivec2();
+        let step = IVec2::new(delta.x.signum() as i32, delta.y.signum() as i32);
+
+        // How far along the line (0.0..=1.0) we move when crossing one cell on each axis,
+        // and how far along the line the next cell border on each axis is
+        let axis = |delta: f32, from: f32, cell: i32| {
+            if delta == 0.0 {
+                return (f32::INFINITY, f32::INFINITY);
+            }
+            let t_delta = (1.0 / delta).abs();
+            let to_border = if delta > 0.0 { cell as f32 + 1.0 - from } else { from - cell as f32 };
+            (t_delta, to_border * t_delta)
+        };
+        let (t_delta_x, mut t_max_x) = axis(delta.x, from.x, cell.x);
+        let (t_delta_y, mut t_max_y) = axis(delta.y, from.y, cell.y);
+
+        let steps = (end - cell).abs();
+        for _ in 0..steps.x + steps.y {
+            if t_max_x < t_max_y {
+                t_max_x += t_delta_x;
+                cell.x += step.x;
+            } else {
+                t_max_y += t_delta_y;
+                cell.y += step.y;
+            }
+
+            if self.cell(cell.x, cell.y) != MapCell::Empty {
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -0,0 +1,71 @@
+// Protocol of the master server, which keeps the public list of game servers
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Game servers send a heartbeat this often to stay listed
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Servers are delisted after going this long without a heartbeat
+pub const LISTING_EXPIRY: Duration = Duration::from_secs(35);
+
+/// The list is split into pages, so each fits in a single datagram
+pub const SERVERS_PER_MESSAGE: usize = 8;
+
+/// How many list requests and challenges the master server answers per IP and [LIST_RATE_WINDOW]
+pub const MAX_LIST_REQUESTS: u32 = 32;
+pub const LIST_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// How many game servers a single IP can have listed at once
+pub const MAX_LISTINGS_PER_IP: usize = 4;
+
+/// Longest name the master server lists, longer ones are cut short
+pub const MAX_SERVER_NAME_LENGTH: usize = 48;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ToMasterMessage {
+    /// Sent by game servers, registers the server or keeps it listed
+    Heartbeat(Heartbeat),
+    /// Sent by game servers shutting down, the port is the one given in the heartbeats.
+    /// Only delists the server if the nonce is the one it was listed with.
+    Unregister { port: u16, nonce: u64 },
+    /// Sent by clients, answered with a single [FromMasterMessage::ServerList] holding the given page.
+    /// One datagram per request keeps the master server from sending more than it's asked for.
+    List { page: u32 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub name: String,
+    /// Port the game runs on, the IP is the one the heartbeat came from
+    pub port: u16,
+    pub players: usize,
+    pub max_players: usize,
+    pub protocol_version: u32,
+    /// Nonce from the last [FromMasterMessage::Challenge], None until the master server sent one
+    pub nonce: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FromMasterMessage {
+    /// Answer to a heartbeat without the right nonce, the server is only listed once it repeats the nonce.
+    /// Proves the heartbeat came from the address it claims, as a spoofed one never gets to see it.
+    Challenge { port: u16, nonce: u64 },
+    /// One page of the list, there's always at least one even if no servers are listed
+    ServerList {
+        page: u32,
+        pages: u32,
+        servers: Vec<ListedServer>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListedServer {
+    pub addr: SocketAddr,
+    pub name: String,
+    pub players: usize,
+    pub max_players: usize,
+    pub protocol_version: u32,
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::net::{Channel, Packet, Sequence};
+
+/// How long a reliable packet can go unacknowledged before it's sent again
+pub const RESEND_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// A peer that lets this many reliable packets go unacknowledged isn't keeping up and should be dropped
+pub const MAX_UNACKED: usize = 1024;
+
+/// Most packets resent per timeout, oldest first, so a backlog doesn't go out all at once
+const MAX_RESEND_BATCH: usize = 64;
+
+/// Sequences less than half the range ahead of the expected one are new, the rest were already delivered
+const HALF_RANGE: Sequence = Sequence::MAX / 2 + 1;
+
+#[derive(Debug)]
+struct Unacked {
+    packet: Packet,
+    sent_at: Instant,
+}
+
+/// Sending half of a reliable ordered channel.
+/// Keeps every packet around until it's acknowledged so it can be resent.
+#[derive(Debug, Default)]
+pub struct ReliableSender {
+    next_sequence: Sequence,
+    unacked: BTreeMap<Sequence, Unacked>,
+}
+
+impl ReliableSender {
+    /// Assigns the next sequence number to the packet and returns it ready to send
+    pub fn send(&mut self, mut packet: Packet) -> Packet {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+
+        packet.channel = Channel::Reliable(sequence);
+        self.unacked.insert(
+            sequence,
+            Unacked {
+                packet: packet.clone(),
+                sent_at: Instant::now(),
+            },
+        );
+
+        packet
+    }
+
+    /// Marks a sequence as received, returns false if it wasn't waiting for an ack
+    pub fn ack(&mut self, sequence: Sequence) -> bool {
+        self.unacked.remove(&sequence).is_some()
+    }
+
+    /// Returns the packets that have gone unacknowledged for longer than `timeout` and resets their timers.
+    /// At most [MAX_RESEND_BATCH] are returned, the rest wait for the next call.
+    pub fn resend(&mut self, timeout: Duration) -> Vec<Packet> {
+        let now = Instant::now();
+
+        self.unacked
+            .iter_mut()
+            .filter(|(_, unacked)| now - unacked.sent_at >= timeout)
+            .take(MAX_RESEND_BATCH)
+            .map(|(_, unacked)| {
+                unacked.sent_at = now;
+                unacked.packet.clone()
+            })
+            .collect()
+    }
+
+    /// Number of packets still waiting for an ack
+    pub fn unacked(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// Whether more than [MAX_UNACKED] packets are waiting, the peer should be disconnected
+    pub fn is_overflowing(&self) -> bool {
+        self.unacked.len() > MAX_UNACKED
+    }
+}
+
+/// Receiving half of a reliable ordered channel.
+/// Holds back packets that arrive early and drops duplicates.
+#[derive(Debug, Default)]
+pub struct ReliableReceiver {
+    next_sequence: Sequence,
+    buffered: BTreeMap<Sequence, Packet>,
+}
+
+impl ReliableReceiver {
+    /// Accepts a packet that was sent with the given sequence.
+    /// Returns every packet that can now be delivered, in order.
+    pub fn receive(&mut self, sequence: Sequence, packet: Packet) -> Vec<Packet> {
+        // Sequences wrap around, so "ahead" is measured from the expected sequence
+        if sequence.wrapping_sub(self.next_sequence) < HALF_RANGE {
+            self.buffered.entry(sequence).or_insert(packet);
+        }
+
+        let mut ready = Vec::new();
+        while let Some(packet) = self.buffered.remove(&self.next_sequence) {
+            ready.push(packet);
+            self.next_sequence = self.next_sequence.wrapping_add(1);
+        }
+
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(byte: u8) -> Packet {
+        Packet::unreliable(vec![byte])
+    }
+
+    #[test]
+    fn delivers_in_order_across_wraparound() {
+        let mut receiver = ReliableReceiver {
+            next_sequence: Sequence::MAX - 1,
+            ..Default::default()
+        };
+
+        assert!(receiver.receive(0, packet(2)).is_empty());
+        let ready = receiver.receive(Sequence::MAX - 1, packet(0));
+        assert_eq!(ready.len(), 1);
+        let ready = receiver.receive(Sequence::MAX, packet(1));
+        assert_eq!(ready.iter().map(|p| p.data[0]).collect::<Vec<_>>(), vec![1, 2]);
+
+        let ready = receiver.receive(1, packet(3));
+        assert_eq!(ready.len(), 1);
+        // Already delivered before the wrap
+        assert!(receiver.receive(Sequence::MAX, packet(1)).is_empty());
+    }
+
+    #[test]
+    fn overflows_past_the_cap() {
+        let mut sender = ReliableSender::default();
+        for _ in 0..MAX_UNACKED {
+            sender.send(packet(0));
+        }
+        assert!(!sender.is_overflowing());
+
+        sender.send(packet(0));
+        assert!(sender.is_overflowing());
+    }
+}
@@ -0,0 +1,841 @@
+// Compact bit-packed wire format for everything sent between the game client and server.
+//
+// Every enum is sent as a varint tag followed by a length prefixed section,
+// so a reader that doesn't know a tag can skip it and a reader that knows fewer fields than the writer ignores the rest.
+// Tags are part of the protocol: never reuse or reorder them, only add new ones.
+
+use std::error::Error;
+use std::f32::consts::TAU;
+use std::fmt::Display;
+use std::num::NonZeroU64;
+
+use glam::Vec2;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::ecs::components::{EcsProtocol, InputState};
+use crate::gun::Gun;
+use crate::handshake::JoinRequest;
+use crate::net::fragment::Fragment;
+use crate::net::{Channel, Packet};
+use crate::snapshot::Snapshot;
+use crate::{ClientMessage, FromClientMessage, FromServerMessage};
+
+/// Positions and velocities are sent as fixed point numbers with this many steps per unit
+pub const POSITION_SCALE: f32 = 256.0;
+
+/// Angles are sent as this many bits of a full turn
+pub const ANGLE_BITS: u32 = 16;
+
+/// Tag no server message may ever use, it's the first byte of [compressed](crate::net::compression) messages.
+/// The largest tag that still fits in a single byte.
+pub const RESERVED_TAG: u64 = 127;
+
+#[derive(Debug)]
+pub enum CodecError {
+    /// The data ended in the middle of a value
+    UnexpectedEnd,
+    /// A tag this build doesn't know, probably sent by a newer build
+    UnknownTag(u64),
+    Invalid(&'static str),
+    Bincode(bincode::Error),
+}
+
+impl From<bincode::Error> for CodecError {
+    fn from(value: bincode::Error) -> Self {
+        CodecError::Bincode(value)
+    }
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::UnexpectedEnd => write!(f, "Message ended unexpectedly"),
+            CodecError::UnknownTag(tag) => write!(f, "Unknown tag {tag}"),
+            CodecError::Invalid(what) => write!(f, "Invalid {what}"),
+            CodecError::Bincode(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for CodecError {}
+
+pub type Result<T> = std::result::Result<T, CodecError>;
+
+/// A type that can be written to and read from the wire
+pub trait WireFormat: Sized {
+    fn encode(&self, writer: &mut BitWriter);
+    fn decode(reader: &mut BitReader) -> Result<Self>;
+}
+
+pub fn encode<T: WireFormat>(value: &T) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    value.encode(&mut writer);
+    writer.finish()
+}
+
+pub fn decode<T: WireFormat>(data: &[u8]) -> Result<T> {
+    T::decode(&mut BitReader::new(data))
+}
+
+/// Packs values into bytes starting from the least significant bit
+#[derive(Debug, Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    /// Writes the lowest `count` bits of the value
+    pub fn write_bits(&mut self, mut value: u64, mut count: u32) {
+        debug_assert!(count <= 64);
+        if count < 64 {
+            value &= (1 << count) - 1;
+        }
+
+        while count > 0 {
+            let offset = (self.bits % 8) as u32;
+            if offset == 0 {
+                self.bytes.push(0);
+            }
+            let take = (8 - offset).min(count);
+            let last = self.bytes.last_mut().unwrap();
+            *last |= ((value & ((1 << take) - 1)) as u8) << offset;
+
+            value >>= take;
+            count -= take;
+            self.bits += take as usize;
+        }
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_bits(value as u64, 1);
+    }
+
+    /// Small numbers take fewer bits, 7 bits of the value at a time followed by a bit saying if more follow
+    pub fn write_varint(&mut self, mut value: u64) {
+        loop {
+            self.write_bits(value & 0x7f, 7);
+            value >>= 7;
+            self.write_bool(value != 0);
+            if value == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Zigzag encoded so small negative numbers stay small too
+    pub fn write_signed(&mut self, value: i64) {
+        self.write_varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bits(value.to_bits() as u64, 32);
+    }
+
+    /// Writes a number rounded to the nearest `1 / scale`
+    pub fn write_fixed(&mut self, value: f32, scale: f32) {
+        self.write_signed((value * scale).round() as i64);
+    }
+
+    pub fn write_position(&mut self, value: Vec2) {
+        self.write_fixed(value.x, POSITION_SCALE);
+        self.write_fixed(value.y, POSITION_SCALE);
+    }
+
+    /// Writes an angle in radians, anything outside a single turn is wrapped
+    pub fn write_angle(&mut self, angle: f32) {
+        let steps = (1u64 << ANGLE_BITS) as f32;
+        let turn = angle.rem_euclid(TAU) / TAU;
+        self.write_bits((turn * steps).round() as u64 % (1 << ANGLE_BITS), ANGLE_BITS);
+    }
+
+    /// Writes a direction as its angle, the length is lost
+    pub fn write_direction(&mut self, direction: Vec2) {
+        self.write_angle(direction.y.atan2(direction.x));
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_varint(bytes.len() as u64);
+        for &byte in bytes {
+            self.write_bits(byte as u64, 8);
+        }
+    }
+
+    /// Writes whatever `f` writes prefixed with its length in bits, so readers can skip it
+    pub fn write_section(&mut self, f: impl FnOnce(&mut BitWriter)) {
+        let mut section = BitWriter::default();
+        f(&mut section);
+
+        self.write_varint(section.bits as u64);
+        let mut remaining = section.bits;
+        for &byte in &section.bytes {
+            let count = remaining.min(8);
+            self.write_bits(byte as u64, count as u32);
+            remaining -= count;
+        }
+    }
+
+    /// Returns the written bytes, the last one is padded with zeroes
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads values written by a [BitWriter]
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    end: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        BitReader {
+            bytes,
+            position: 0,
+            end: bytes.len() * 8,
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.end - self.position
+    }
+
+    pub fn read_bits(&mut self, count: u32) -> Result<u64> {
+        debug_assert!(count <= 64);
+        if self.remaining() < count as usize {
+            return Err(CodecError::UnexpectedEnd);
+        }
+
+        let mut value = 0;
+        let mut read = 0;
+        while read < count {
+            let offset = (self.position % 8) as u32;
+            let take = (8 - offset).min(count - read);
+            let byte = (self.bytes[self.position / 8] >> offset) as u64;
+            value |= (byte & ((1 << take) - 1)) << read;
+
+            read += take;
+            self.position += take as usize;
+        }
+        Ok(value)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    pub fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            if shift >= 64 {
+                return Err(CodecError::Invalid("varint"));
+            }
+            value |= self.read_bits(7)? << shift;
+            shift += 7;
+            if !self.read_bool()? {
+                return Ok(value);
+            }
+        }
+    }
+
+    pub fn read_signed(&mut self) -> Result<i64> {
+        let value = self.read_varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.read_bits(32)? as u32))
+    }
+
+    pub fn read_fixed(&mut self, scale: f32) -> Result<f32> {
+        Ok(self.read_signed()? as f32 / scale)
+    }
+
+    pub fn read_position(&mut self) -> Result<Vec2> {
+        Ok(Vec2::new(self.read_fixed(POSITION_SCALE)?, self.read_fixed(POSITION_SCALE)?))
+    }
+
+    /// Reads an angle in radians between 0 and a full turn
+    pub fn read_angle(&mut self) -> Result<f32> {
+        let steps = (1u64 << ANGLE_BITS) as f32;
+        Ok(self.read_bits(ANGLE_BITS)? as f32 / steps * TAU)
+    }
+
+    /// Reads a direction of length 1
+    pub fn read_direction(&mut self) -> Result<Vec2> {
+        Ok(Vec2::from_angle(self.read_angle()?))
+    }
+
+    pub fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_varint()? as usize;
+        if len > self.remaining() / 8 {
+            return Err(CodecError::UnexpectedEnd);
+        }
+        (0..len).map(|_| self.read_bits(8).map(|byte| byte as u8)).collect()
+    }
+
+    /// Reads a section written by [BitWriter::write_section] and skips past it in this reader.
+    /// Whatever the returned reader doesn't read is ignored.
+    pub fn read_section(&mut self) -> Result<BitReader<'a>> {
+        let len = self.read_varint()?;
+        if len > self.remaining() as u64 {
+            return Err(CodecError::UnexpectedEnd);
+        }
+
+        let section = BitReader {
+            bytes: self.bytes,
+            position: self.position,
+            end: self.position + len as usize,
+        };
+        self.position += len as usize;
+        Ok(section)
+    }
+}
+
+/// Writes a value that rarely gets sent with bincode instead of a hand written format
+pub fn encode_serde<T: Serialize>(value: &T, writer: &mut BitWriter) {
+    let data = bincode::serialize(value).expect("Messages should always be serializable");
+    writer.write_bytes(&data);
+}
+
+pub fn decode_serde<T: DeserializeOwned>(reader: &mut BitReader) -> Result<T> {
+    Ok(bincode::deserialize(&reader.read_bytes()?)?)
+}
+
+// Primitives
+
+impl WireFormat for bool {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_bool(*self);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        reader.read_bool()
+    }
+}
+
+impl WireFormat for u16 {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_varint(*self as u64);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        reader.read_varint()?.try_into().map_err(|_| CodecError::Invalid("u16"))
+    }
+}
+
+impl WireFormat for u32 {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_varint(*self as u64);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        reader.read_varint()?.try_into().map_err(|_| CodecError::Invalid("u32"))
+    }
+}
+
+impl WireFormat for u64 {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_varint(*self);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        reader.read_varint()
+    }
+}
+
+impl WireFormat for usize {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_varint(*self as u64);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        reader.read_varint()?.try_into().map_err(|_| CodecError::Invalid("usize"))
+    }
+}
+
+impl WireFormat for i32 {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_signed(*self as i64);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        reader.read_signed()?.try_into().map_err(|_| CodecError::Invalid("i32"))
+    }
+}
+
+impl WireFormat for f32 {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_f32(*self);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        reader.read_f32()
+    }
+}
+
+impl WireFormat for String {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_bytes(self.as_bytes());
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        String::from_utf8(reader.read_bytes()?).map_err(|_| CodecError::Invalid("string"))
+    }
+}
+
+impl<T: WireFormat> WireFormat for Option<T> {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_bool(self.is_some());
+        if let Some(value) = self {
+            value.encode(writer);
+        }
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        if reader.read_bool()? {
+            Ok(Some(T::decode(reader)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Entity ids are hecs entity bits, the generation and index are sent separately as both are usually small
+impl WireFormat for NonZeroU64 {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_varint(self.get() & u32::MAX as u64);
+        writer.write_varint(self.get() >> 32);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        let index = u32::decode(reader)? as u64;
+        let generation = u32::decode(reader)? as u64;
+        NonZeroU64::new(generation << 32 | index).ok_or(CodecError::Invalid("entity id"))
+    }
+}
+
+impl WireFormat for Gun {
+    fn encode(&self, writer: &mut BitWriter) {
+        let tag = match self {
+            Gun::Pistol => 0,
+            Gun::Sniper => 1,
+            Gun::Shotgun => 2,
+            Gun::SubMachineGun => 3,
+            Gun::AssaultRifle => 4,
+            Gun::MachineGun => 5,
+        };
+        writer.write_varint(tag);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        match reader.read_varint()? {
+            0 => Ok(Gun::Pistol),
+            1 => Ok(Gun::Sniper),
+            2 => Ok(Gun::Shotgun),
+            3 => Ok(Gun::SubMachineGun),
+            4 => Ok(Gun::AssaultRifle),
+            5 => Ok(Gun::MachineGun),
+            tag => Err(CodecError::UnknownTag(tag)),
+        }
+    }
+}
+
+// ECS
+
+impl WireFormat for EcsProtocol {
+    fn encode(&self, writer: &mut BitWriter) {
+        match self {
+            EcsProtocol::Insert((entity, component)) => {
+                writer.write_varint(0);
+                entity.encode(writer);
+                component.encode(writer);
+            }
+            EcsProtocol::Remove((entity, component)) => {
+                writer.write_varint(1);
+                entity.encode(writer);
+                component.encode(writer);
+            }
+            EcsProtocol::Despawn(entity) => {
+                writer.write_varint(2);
+                entity.encode(writer);
+            }
+        }
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        match reader.read_varint()? {
+            0 => Ok(EcsProtocol::Insert((NonZeroU64::decode(reader)?, WireFormat::decode(reader)?))),
+            1 => Ok(EcsProtocol::Remove((NonZeroU64::decode(reader)?, WireFormat::decode(reader)?))),
+            2 => Ok(EcsProtocol::Despawn(NonZeroU64::decode(reader)?)),
+            _ => Err(CodecError::Invalid("ecs operation")),
+        }
+    }
+}
+
+pub fn encode_changes(changes: &[EcsProtocol], writer: &mut BitWriter) {
+    writer.write_varint(changes.len() as u64);
+    for change in changes {
+        change.encode(writer);
+    }
+}
+
+/// Changes to components this build doesn't know about are left out
+pub fn decode_changes(reader: &mut BitReader) -> Result<Vec<EcsProtocol>> {
+    let len = reader.read_varint()? as usize;
+    let mut changes = Vec::with_capacity(len.min(reader.remaining()));
+    for _ in 0..len {
+        match EcsProtocol::decode(reader) {
+            Ok(change) => changes.push(change),
+            // The component's section has already been skipped
+            Err(CodecError::UnknownTag(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(changes)
+}
+
+impl WireFormat for Snapshot {
+    fn encode(&self, writer: &mut BitWriter) {
+        self.tick.encode(writer);
+        self.baseline.encode(writer);
+        encode_changes(&self.changes, writer);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        Ok(Snapshot {
+            tick: WireFormat::decode(reader)?,
+            baseline: WireFormat::decode(reader)?,
+            changes: decode_changes(reader)?,
+        })
+    }
+}
+
+impl WireFormat for InputState {
+    fn encode(&self, writer: &mut BitWriter) {
+        writer.write_bool(self.forward);
+        writer.write_bool(self.backward);
+        writer.write_bool(self.left);
+        writer.write_bool(self.right);
+        writer.write_bool(self.shoot);
+        writer.write_angle(self.look_angle);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        Ok(InputState {
+            forward: reader.read_bool()?,
+            backward: reader.read_bool()?,
+            left: reader.read_bool()?,
+            right: reader.read_bool()?,
+            shoot: reader.read_bool()?,
+            look_angle: reader.read_angle()?,
+        })
+    }
+}
+
+// Messages
+
+impl WireFormat for Packet {
+    fn encode(&self, writer: &mut BitWriter) {
+        self.sequence.encode(writer);
+        match self.channel {
+            Channel::Unreliable => writer.write_bool(false),
+            Channel::Reliable(sequence) => {
+                writer.write_bool(true);
+                sequence.encode(writer);
+            }
+        }
+        self.fragment.encode(writer);
+        writer.write_bytes(&self.data);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        Ok(Packet {
+            sequence: WireFormat::decode(reader)?,
+            channel: if reader.read_bool()? {
+                Channel::Reliable(WireFormat::decode(reader)?)
+            } else {
+                Channel::Unreliable
+            },
+            fragment: WireFormat::decode(reader)?,
+            data: reader.read_bytes()?,
+        })
+    }
+}
+
+impl WireFormat for Fragment {
+    fn encode(&self, writer: &mut BitWriter) {
+        self.message_id.encode(writer);
+        self.index.encode(writer);
+        self.count.encode(writer);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        Ok(Fragment {
+            message_id: WireFormat::decode(reader)?,
+            index: WireFormat::decode(reader)?,
+            count: WireFormat::decode(reader)?,
+        })
+    }
+}
+
+impl WireFormat for JoinRequest {
+    fn encode(&self, writer: &mut BitWriter) {
+        // Comes first so any future version can still read it and tell the versions don't match
+        self.protocol_version.encode(writer);
+        self.build.encode(writer);
+        self.username.encode(writer);
+        self.spectate.encode(writer);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        let protocol_version = WireFormat::decode(reader)?;
+        let build = WireFormat::decode(reader)?;
+        let username = WireFormat::decode(reader)?;
+        // Builds before spectating didn't send it, they still need to decode to be told their version is wrong
+        let spectate = match reader.remaining() {
+            0 => false,
+            _ => WireFormat::decode(reader)?,
+        };
+
+        Ok(JoinRequest {
+            protocol_version,
+            build,
+            username,
+            spectate,
+        })
+    }
+}
+
+impl WireFormat for ClientMessage {
+    fn encode(&self, writer: &mut BitWriter) {
+        // Tokens are random, a varint would only make them longer
+        writer.write_bool(self.token.is_some());
+        if let Some(token) = self.token {
+            writer.write_bits(token, 64);
+        }
+        self.sequence.encode(writer);
+        self.message.encode(writer);
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        let token = if reader.read_bool()? {
+            Some(reader.read_bits(64)?)
+        } else {
+            None
+        };
+
+        Ok(ClientMessage {
+            token,
+            sequence: WireFormat::decode(reader)?,
+            message: WireFormat::decode(reader)?,
+        })
+    }
+}
+
+impl WireFormat for FromClientMessage {
+    fn encode(&self, writer: &mut BitWriter) {
+        let tag = match self {
+            FromClientMessage::Ping => 0,
+            FromClientMessage::Leave => 1,
+            FromClientMessage::Join(_) => 2,
+            FromClientMessage::Resume(_) => 3,
+            FromClientMessage::UpdateInputs(..) => 4,
+            FromClientMessage::AckSnapshot(_) => 5,
+            FromClientMessage::Ack(_) => 6,
+            FromClientMessage::Query => 7,
+            FromClientMessage::Chat(_) => 8,
+            FromClientMessage::Play => 9,
+        };
+        writer.write_varint(tag);
+
+        writer.write_section(|writer| match self {
+            FromClientMessage::Ping | FromClientMessage::Leave | FromClientMessage::Query | FromClientMessage::Play => {}
+            FromClientMessage::Join(request) | FromClientMessage::Resume(request) => request.encode(writer),
+            FromClientMessage::UpdateInputs(sequence, input, view_tick) => {
+                sequence.encode(writer);
+                input.encode(writer);
+                view_tick.encode(writer);
+            }
+            FromClientMessage::AckSnapshot(tick) => tick.encode(writer),
+            FromClientMessage::Ack(sequence) => sequence.encode(writer),
+            FromClientMessage::Chat(text) => text.encode(writer),
+        });
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        let tag = reader.read_varint()?;
+        let reader = &mut reader.read_section()?;
+
+        match tag {
+            0 => Ok(FromClientMessage::Ping),
+            1 => Ok(FromClientMessage::Leave),
+            2 => Ok(FromClientMessage::Join(WireFormat::decode(reader)?)),
+            3 => Ok(FromClientMessage::Resume(WireFormat::decode(reader)?)),
+            4 => Ok(FromClientMessage::UpdateInputs(
+                WireFormat::decode(reader)?,
+                WireFormat::decode(reader)?,
+                WireFormat::decode(reader)?,
+            )),
+            5 => Ok(FromClientMessage::AckSnapshot(WireFormat::decode(reader)?)),
+            6 => Ok(FromClientMessage::Ack(WireFormat::decode(reader)?)),
+            7 => Ok(FromClientMessage::Query),
+            8 => Ok(FromClientMessage::Chat(WireFormat::decode(reader)?)),
+            9 => Ok(FromClientMessage::Play),
+            tag => Err(CodecError::UnknownTag(tag)),
+        }
+    }
+}
+
+impl WireFormat for FromServerMessage {
+    fn encode(&self, writer: &mut BitWriter) {
+        let tag = match self {
+            // Has to stay 0, so clients of any version can decode a rejection
+            FromServerMessage::JoinResponse(_) => 0,
+            FromServerMessage::OwnId(_) => 1,
+            FromServerMessage::SendMap(_) => 2,
+            FromServerMessage::Pong => 3,
+            FromServerMessage::EcsChanges(_) => 4,
+            FromServerMessage::Snapshot(_) => 5,
+            FromServerMessage::Disconnect(_) => 6,
+            FromServerMessage::Scoreboard(_) => 7,
+            FromServerMessage::ServerInfo(_) => 8,
+            FromServerMessage::Chat(_) => 9,
+            FromServerMessage::Spectating => 10,
+            FromServerMessage::NoFreeSlot => 11,
+            // RESERVED_TAG marks compressed messages
+        };
+        writer.write_varint(tag);
+
+        // Only the messages sent every tick are worth a hand written format
+        writer.write_section(|writer| match self {
+            FromServerMessage::JoinResponse(response) => encode_serde(response, writer),
+            FromServerMessage::OwnId(id) => id.encode(writer),
+            FromServerMessage::SendMap(map) => encode_serde(map, writer),
+            FromServerMessage::Pong | FromServerMessage::Spectating | FromServerMessage::NoFreeSlot => {}
+            FromServerMessage::EcsChanges(changes) => encode_changes(changes, writer),
+            FromServerMessage::Snapshot(snapshot) => snapshot.encode(writer),
+            FromServerMessage::Disconnect(reason) => encode_serde(reason, writer),
+            FromServerMessage::Scoreboard(scoreboard) => encode_serde(scoreboard, writer),
+            FromServerMessage::ServerInfo(info) => encode_serde(info, writer),
+            FromServerMessage::Chat(message) => encode_serde(message, writer),
+        });
+    }
+
+    fn decode(reader: &mut BitReader) -> Result<Self> {
+        let tag = reader.read_varint()?;
+        let reader = &mut reader.read_section()?;
+
+        match tag {
+            0 => Ok(FromServerMessage::JoinResponse(decode_serde(reader)?)),
+            1 => Ok(FromServerMessage::OwnId(WireFormat::decode(reader)?)),
+            2 => Ok(FromServerMessage::SendMap(decode_serde(reader)?)),
+            3 => Ok(FromServerMessage::Pong),
+            4 => Ok(FromServerMessage::EcsChanges(decode_changes(reader)?)),
+            5 => Ok(FromServerMessage::Snapshot(WireFormat::decode(reader)?)),
+            6 => Ok(FromServerMessage::Disconnect(decode_serde(reader)?)),
+            7 => Ok(FromServerMessage::Scoreboard(decode_serde(reader)?)),
+            8 => Ok(FromServerMessage::ServerInfo(decode_serde(reader)?)),
+            9 => Ok(FromServerMessage::Chat(decode_serde(reader)?)),
+            10 => Ok(FromServerMessage::Spectating),
+            11 => Ok(FromServerMessage::NoFreeSlot),
+            tag => Err(CodecError::UnknownTag(tag)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::components::{InsertComponent, LookDirection, Player, Position, RemoveComponent};
+    use crate::handshake::PROTOCOL_VERSION;
+
+    use super::*;
+
+    #[test]
+    fn bits_round_trip() {
+        let mut writer = BitWriter::default();
+        writer.write_bits(0b101, 3);
+        writer.write_varint(300);
+        writer.write_signed(-2);
+        writer.write_bits(u64::MAX, 64);
+        let data = writer.finish();
+
+        let mut reader = BitReader::new(&data);
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+        assert_eq!(reader.read_varint().unwrap(), 300);
+        assert_eq!(reader.read_signed().unwrap(), -2);
+        assert_eq!(reader.read_bits(64).unwrap(), u64::MAX);
+        assert!(reader.read_bits(8).is_err());
+    }
+
+    #[test]
+    fn changes_round_trip() {
+        let entity = NonZeroU64::new(1 << 32 | 7).unwrap();
+        let changes = vec![
+            EcsProtocol::Insert((entity, InsertComponent::Position(Position(Vec2::new(1.5, -20.25))))),
+            EcsProtocol::Insert((entity, InsertComponent::Player(Player { id: 3, name: "test".to_string() }))),
+            EcsProtocol::Remove((entity, RemoveComponent::Health)),
+            EcsProtocol::Despawn(entity),
+        ];
+        let message = FromServerMessage::EcsChanges(changes.clone());
+
+        match decode(&encode(&message)).unwrap() {
+            FromServerMessage::EcsChanges(decoded) => assert_eq!(decoded, changes),
+            other => panic!("Decoded the wrong message: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn directions_are_quantized() {
+        let direction = Vec2::from_angle(1.0);
+        let mut writer = BitWriter::default();
+        LookDirection(direction).encode(&mut writer);
+        let data = writer.finish();
+
+        let decoded = LookDirection::decode(&mut BitReader::new(&data)).unwrap();
+        assert!((decoded.0 - direction).length() < 0.001);
+    }
+
+    #[test]
+    fn old_join_requests_still_decode() {
+        // The shape of a join request from before spectating existed
+        let mut writer = BitWriter::default();
+        writer.write_varint(2);
+        writer.write_section(|writer| {
+            2u32.encode(writer);
+            "0.1.0".to_string().encode(writer);
+            "old".to_string().encode(writer);
+        });
+        let data = writer.finish();
+
+        match FromClientMessage::decode(&mut BitReader::new(&data)).unwrap() {
+            FromClientMessage::Join(request) => {
+                assert_ne!(request.protocol_version, PROTOCOL_VERSION);
+                assert_eq!(request.username, "old");
+                assert!(!request.spectate);
+            }
+            other => panic!("Decoded the wrong message: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_components_are_skipped() {
+        let entity = NonZeroU64::new(1).unwrap();
+        let mut writer = BitWriter::default();
+        writer.write_varint(2);
+        // A component from a newer build, with a tag nothing uses yet
+        writer.write_varint(0);
+        entity.encode(&mut writer);
+        writer.write_varint(1000);
+        writer.write_section(|writer| writer.write_bytes(b"from the future"));
+        EcsProtocol::Despawn(entity).encode(&mut writer);
+        let data = writer.finish();
+
+        let changes = decode_changes(&mut BitReader::new(&data)).unwrap();
+        assert_eq!(changes, vec![EcsProtocol::Despawn(entity)]);
+    }
+}
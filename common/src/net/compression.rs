@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::net::codec::RESERVED_TAG;
+use crate::net::fragment::{MAX_FRAGMENTS, MAX_FRAGMENT_SIZE};
+
+/// Messages smaller than this are sent as is, compressing them wouldn't save enough to be worth it
+pub const COMPRESSION_THRESHOLD: usize = 512;
+
+/// Largest message a compressed one may expand to, the same as the largest message that can be fragmented
+const MAX_MESSAGE_SIZE: usize = MAX_FRAGMENTS as usize * MAX_FRAGMENT_SIZE;
+
+/// First byte of compressed messages, it reads as the reserved tag which no message starts with.
+/// Everything else is sent exactly as encoded, so any build can still read uncompressed messages.
+pub const COMPRESSED: u8 = RESERVED_TAG as u8;
+
+#[derive(Debug)]
+pub enum CompressionError {
+    /// Decompressed to more than any message can be
+    TooLarge,
+    Io(io::Error),
+}
+
+impl From<io::Error> for CompressionError {
+    fn from(value: io::Error) -> Self {
+        CompressionError::Io(value)
+    }
+}
+
+impl Display for CompressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompressionError::TooLarge => write!(f, "Message decompressed to over {MAX_MESSAGE_SIZE} bytes"),
+            CompressionError::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for CompressionError {}
+
+/// Compresses an encoded message behind the [COMPRESSED] marker if it's large and compresses well
+pub fn pack(data: Vec<u8>) -> Vec<u8> {
+    if data.len() >= COMPRESSION_THRESHOLD {
+        let mut encoder = DeflateEncoder::new(vec![COMPRESSED], Compression::default());
+        encoder.write_all(&data).expect("Writing to a Vec can't fail");
+        let compressed = encoder.finish().expect("Writing to a Vec can't fail");
+
+        if compressed.len() < data.len() {
+            return compressed;
+        }
+    }
+
+    data
+}
+
+/// Returns the encoded message, decompressed if it starts with the [COMPRESSED] marker
+pub fn unpack(data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let data = match data.split_first() {
+        Some((&COMPRESSED, data)) => data,
+        _ => return Ok(data.to_vec()),
+    };
+
+    // Reading one byte past the limit tells a message that's exactly at the limit apart from one that's over it
+    let mut decompressed = Vec::new();
+    DeflateDecoder::new(data)
+        .take(MAX_MESSAGE_SIZE as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() > MAX_MESSAGE_SIZE {
+        return Err(CompressionError::TooLarge);
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::Map;
+    use crate::net::codec;
+    use crate::FromServerMessage;
+
+    use super::*;
+
+    #[test]
+    fn small_messages_are_not_compressed() {
+        let data = codec::encode(&FromServerMessage::Pong);
+        let packed = pack(data.clone());
+
+        assert_eq!(packed, data);
+        assert_eq!(unpack(&packed).unwrap(), data);
+    }
+
+    #[test]
+    fn marker_reads_as_the_reserved_tag() {
+        let mut reader = codec::BitReader::new(&[COMPRESSED]);
+        assert_eq!(reader.read_varint().unwrap(), RESERVED_TAG);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn generated_map_compresses() {
+        let data = codec::encode(&FromServerMessage::SendMap(Map::gen(41, 41)));
+        let packed = pack(data.clone());
+
+        assert_eq!(packed[0], COMPRESSED);
+        assert!(
+            packed.len() * 4 < data.len(),
+            "Map only compressed from {} to {} bytes",
+            data.len(),
+            packed.len()
+        );
+        assert_eq!(unpack(&packed).unwrap(), data);
+    }
+}
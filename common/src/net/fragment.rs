@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::net::Packet;
+
+/// Largest amount of message data put into a single packet.
+/// Leaves room for the packet and UDP/IP headers within a typical 1500 byte MTU.
+pub const MAX_FRAGMENT_SIZE: usize = 1200;
+
+/// Upper limit on how many fragments a single message can be split into (about 78 MB)
+pub const MAX_FRAGMENTS: u16 = u16::MAX;
+
+/// How long a partially received message is kept around waiting for the rest of its fragments
+pub const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many partially received messages can be waiting at once, older ones get dropped to make room
+const MAX_PENDING_MESSAGES: usize = 64;
+
+static NEXT_MESSAGE_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Marks a packet as one part of a message that was too large to send in one go
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fragment {
+    pub message_id: u32,
+    pub index: u16,
+    pub count: u16,
+}
+
+#[derive(Debug)]
+pub enum FragmentError {
+    /// Would take more than [MAX_FRAGMENTS] fragments
+    TooLarge(usize),
+}
+
+impl Display for FragmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FragmentError::TooLarge(size) => write!(f, "Message of {size} bytes is too large to fragment"),
+        }
+    }
+}
+
+impl Error for FragmentError {}
+
+/// Splits message data into unreliable packets that are small enough to send.
+/// Data that already fits is sent as a single packet without fragment info.
+pub fn split(data: Vec<u8>) -> Result<Vec<Packet>, FragmentError> {
+    if data.len() <= MAX_FRAGMENT_SIZE {
+        return Ok(vec![Packet::unreliable(data)]);
+    }
+
+    let count = data.len().div_ceil(MAX_FRAGMENT_SIZE);
+    if count > MAX_FRAGMENTS as usize {
+        return Err(FragmentError::TooLarge(data.len()));
+    }
+
+    let message_id = NEXT_MESSAGE_ID.fetch_add(1, Ordering::Relaxed);
+
+    let packets = data
+        .chunks(MAX_FRAGMENT_SIZE)
+        .enumerate()
+        .map(|(index, chunk)| Packet {
+            fragment: Some(Fragment {
+                message_id,
+                index: index as u16,
+                count: count as u16,
+            }),
+            ..Packet::unreliable(chunk.to_vec())
+        })
+        .collect();
+
+    Ok(packets)
+}
+
+#[derive(Debug)]
+struct Pending {
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+    started: Instant,
+}
+
+/// Puts fragmented messages back together
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    pending: HashMap<u32, Pending>,
+}
+
+impl Reassembler {
+    /// Accepts a packet, returns the message data once all of its fragments have arrived.
+    /// Packets that aren't fragmented are returned as is.
+    pub fn receive(&mut self, packet: Packet) -> Option<Vec<u8>> {
+        let Some(fragment) = packet.fragment else {
+            return Some(packet.data);
+        };
+
+        if fragment.count == 0 || fragment.index >= fragment.count {
+            return None;
+        }
+
+        if !self.pending.contains_key(&fragment.message_id) && self.pending.len() >= MAX_PENDING_MESSAGES {
+            self.drop_oldest();
+        }
+
+        let pending = self
+            .pending
+            .entry(fragment.message_id)
+            .or_insert_with(|| Pending {
+                fragments: vec![None; fragment.count as usize],
+                received: 0,
+                started: Instant::now(),
+            });
+
+        // Disagrees with the fragments we already have, so something is off with this message
+        if pending.fragments.len() != fragment.count as usize {
+            self.pending.remove(&fragment.message_id);
+            return None;
+        }
+
+        let slot = &mut pending.fragments[fragment.index as usize];
+        if slot.is_none() {
+            *slot = Some(packet.data);
+            pending.received += 1;
+        }
+
+        if pending.received < pending.fragments.len() {
+            return None;
+        }
+
+        let pending = self.pending.remove(&fragment.message_id)?;
+        Some(pending.fragments.into_iter().flatten().flatten().collect())
+    }
+
+    /// Drops messages that have been incomplete for longer than `timeout`.
+    /// Returns how many were dropped.
+    pub fn expire(&mut self, timeout: Duration) -> usize {
+        let before = self.pending.len();
+        self.pending
+            .retain(|_, pending| pending.started.elapsed() < timeout);
+        before - self.pending.len()
+    }
+
+    fn drop_oldest(&mut self) {
+        let oldest = self
+            .pending
+            .iter()
+            .min_by_key(|(_, pending)| pending.started)
+            .map(|(&message_id, _)| message_id);
+
+        if let Some(message_id) = oldest {
+            self.pending.remove(&message_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn small_messages_are_not_fragmented() {
+        let packets = split(message(10)).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert!(packets[0].fragment.is_none());
+
+        let mut reassembler = Reassembler::default();
+        assert_eq!(reassembler.receive(packets[0].clone()), Some(message(10)));
+    }
+
+    #[test]
+    fn reassembles_out_of_order() {
+        let data = message(MAX_FRAGMENT_SIZE * 3 + 5);
+        let mut packets = split(data.clone()).unwrap();
+        assert_eq!(packets.len(), 4);
+        packets.reverse();
+        // A duplicate doesn't count twice
+        packets.insert(1, packets[0].clone());
+
+        let mut reassembler = Reassembler::default();
+        let last = packets.pop().unwrap();
+        for packet in packets {
+            assert_eq!(reassembler.receive(packet), None);
+        }
+        assert_eq!(reassembler.receive(last), Some(data));
+    }
+
+    #[test]
+    fn incomplete_messages_expire() {
+        let packets = split(message(MAX_FRAGMENT_SIZE * 2)).unwrap();
+
+        let mut reassembler = Reassembler::default();
+        assert_eq!(reassembler.receive(packets[0].clone()), None);
+        assert_eq!(reassembler.expire(Duration::ZERO), 1);
+
+        // The first half is gone, so the second one alone doesn't complete anything
+        assert_eq!(reassembler.receive(packets[1].clone()), None);
+    }
+
+    #[test]
+    fn refuses_messages_with_too_many_fragments() {
+        let too_large = MAX_FRAGMENT_SIZE * (MAX_FRAGMENTS as usize + 1);
+        assert!(matches!(split(vec![0; too_large]), Err(FragmentError::TooLarge(_))));
+    }
+}
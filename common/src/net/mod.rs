@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::net::fragment::Fragment;
+
+pub mod channel;
+pub mod codec;
+pub mod compression;
+pub mod fragment;
+pub mod simulator;
+pub mod stats;
+pub mod transport;
+
+pub type Sequence = u32;
+
+/// Wraps every datagram the server sends.
+/// The data is an [encoded](codec) [FromServerMessage](crate::FromServerMessage), [compressed](compression) if it's large,
+/// or a part of one if it's fragmented.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Packet {
+    /// Stamped right before sending so the client can measure loss, 0 if it isn't tracked
+    pub sequence: Sequence,
+    pub channel: Channel,
+    pub fragment: Option<Fragment>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Channel {
+    /// Latest-wins data like movement, lost packets are never resent
+    Unreliable,
+    /// Delivered exactly once and in order, resent until the receiver acknowledges the sequence
+    Reliable(Sequence),
+}
+
+impl Packet {
+    pub fn unreliable(data: Vec<u8>) -> Self {
+        Packet {
+            sequence: 0,
+            channel: Channel::Unreliable,
+            fragment: None,
+            data,
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use message_io::network::Endpoint;
+use message_io::node::NodeHandler;
+use rand::Rng;
+
+/// How much longer than the rest a reordered packet is held back
+pub const REORDER_DELAY: Duration = Duration::from_millis(50);
+
+/// A bad connection to simulate, for testing how the game holds up without a real one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetConditions {
+    /// Added to every packet
+    pub latency: Duration,
+    /// Random extra delay between zero and this, on top of the latency
+    pub jitter: Duration,
+    /// Chance between 0 and 1 that a packet is dropped
+    pub loss: f32,
+    /// Chance between 0 and 1 that a packet is sent twice
+    pub duplicate: f32,
+    /// Chance between 0 and 1 that a packet is held back so the ones after it overtake it
+    pub reorder: f32,
+}
+
+impl NetConditions {
+    /// True if the conditions don't change anything, so there is no need to simulate them
+    pub fn is_perfect(&self) -> bool {
+        *self == NetConditions::default()
+    }
+}
+
+/// Command line flags for simulating a bad connection, flattened into the server and client arguments.
+/// They only affect what the program passing them sends.
+#[derive(Args, Debug, Clone)]
+pub struct SimArgs {
+    /// Simulated latency in milliseconds added to everything sent
+    #[arg(long, default_value_t = 0)]
+    pub sim_latency: u64,
+
+    /// Simulated random extra latency in milliseconds
+    #[arg(long, default_value_t = 0)]
+    pub sim_jitter: u64,
+
+    /// Percentage of sent packets to drop
+    #[arg(long, default_value_t = 0.0)]
+    pub sim_loss: f32,
+
+    /// Percentage of sent packets to send twice
+    #[arg(long, default_value_t = 0.0)]
+    pub sim_duplicate: f32,
+
+    /// Percentage of sent packets to delay so later ones overtake them
+    #[arg(long, default_value_t = 0.0)]
+    pub sim_reorder: f32,
+}
+
+impl SimArgs {
+    /// The bad connection to simulate, None if none of the flags were given
+    pub fn conditions(&self) -> Option<NetConditions> {
+        let conditions = NetConditions {
+            latency: Duration::from_millis(self.sim_latency),
+            jitter: Duration::from_millis(self.sim_jitter),
+            loss: self.sim_loss / 100.0,
+            duplicate: self.sim_duplicate / 100.0,
+            reorder: self.sim_reorder / 100.0,
+        };
+
+        Some(conditions).filter(|conditions| !conditions.is_perfect())
+    }
+}
+
+/// Datagram waiting for its simulated delay to pass
+struct Delayed {
+    due: Instant,
+    endpoint: Endpoint,
+    data: Vec<u8>,
+}
+
+impl PartialEq for Delayed {
+    fn eq(&self, other: &Self) -> bool {
+        self.due == other.due
+    }
+}
+
+impl Eq for Delayed {}
+
+impl PartialOrd for Delayed {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Delayed {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.due.cmp(&other.due)
+    }
+}
+
+/// Applies [NetConditions] to outgoing datagrams, delayed ones are sent from a background thread
+struct Simulator {
+    conditions: NetConditions,
+    queue: Mutex<mpsc::Sender<Delayed>>,
+}
+
+impl Simulator {
+    fn start<S: Send + 'static>(handler: NodeHandler<S>, conditions: NetConditions) -> Self {
+        let (sender, receiver) = mpsc::channel::<Delayed>();
+
+        thread::spawn(move || {
+            let mut pending = BinaryHeap::new();
+
+            loop {
+                let now = Instant::now();
+                while pending.peek().is_some_and(|next: &Reverse<Delayed>| next.0.due <= now) {
+                    let Reverse(delayed) = pending.pop().unwrap();
+                    handler.network().send(delayed.endpoint, &delayed.data);
+                }
+
+                let received = match pending.peek() {
+                    Some(Reverse(next)) => receiver.recv_timeout(next.due.saturating_duration_since(now)),
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+
+                match received {
+                    Ok(delayed) => pending.push(Reverse(delayed)),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // Whoever owned the network is gone, nobody is listening for these anymore
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        Simulator {
+            conditions,
+            queue: Mutex::new(sender),
+        }
+    }
+
+    fn send(&self, endpoint: Endpoint, data: &[u8]) {
+        let mut rng = rand::thread_rng();
+        let conditions = &self.conditions;
+
+        if rng.gen::<f32>() < conditions.loss {
+            return;
+        }
+
+        let copies = if rng.gen::<f32>() < conditions.duplicate { 2 } else { 1 };
+        for _ in 0..copies {
+            let mut delay = conditions.latency + conditions.jitter.mul_f32(rng.gen());
+            if rng.gen::<f32>() < conditions.reorder {
+                delay += REORDER_DELAY;
+            }
+
+            let delayed = Delayed {
+                due: Instant::now() + delay,
+                endpoint,
+                data: data.to_vec(),
+            };
+            // Only fails if the thread is gone, in which case the network is shutting down anyway
+            self.queue.lock().unwrap().send(delayed).ok();
+        }
+    }
+}
+
+/// Sends datagrams through message-io, optionally through a simulated bad connection first
+pub struct Network<S: Send + 'static> {
+    handler: NodeHandler<S>,
+    simulator: Option<Arc<Simulator>>,
+}
+
+impl<S: Send + 'static> Clone for Network<S> {
+    fn clone(&self) -> Self {
+        Network {
+            handler: self.handler.clone(),
+            simulator: self.simulator.clone(),
+        }
+    }
+}
+
+impl<S: Send + 'static> Network<S> {
+    pub fn new(handler: NodeHandler<S>) -> Self {
+        Network {
+            handler,
+            simulator: None,
+        }
+    }
+
+    /// Makes everything sent from now on go through the simulated conditions
+    pub fn simulate(&mut self, conditions: NetConditions) {
+        self.simulator = if conditions.is_perfect() {
+            None
+        } else {
+            Some(Arc::new(Simulator::start(self.handler.clone(), conditions)))
+        };
+    }
+
+    pub fn send(&self, endpoint: Endpoint, data: &[u8]) {
+        match &self.simulator {
+            Some(simulator) => simulator.send(endpoint, data),
+            None => {
+                self.handler.network().send(endpoint, data);
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::net::Sequence;
+
+/// Rates and packet loss are measured over windows of this length
+pub const STATS_WINDOW: Duration = Duration::from_secs(1);
+
+/// How many unanswered round trip probes are remembered before the oldest are forgotten
+const MAX_PROBES: usize = 256;
+
+/// Connection quality of one side of a connection.
+///
+/// Round trip time comes from probes, any message the other side is known to answer.
+/// Loss is measured on incoming packets, every tracked packet carries a sequence number and the gaps are lost packets.
+#[derive(Debug)]
+pub struct NetStats {
+    next_sequence: Sequence,
+
+    /// Smoothed round trip time in seconds
+    rtt: Option<f32>,
+    /// Mean deviation between consecutive round trip samples in seconds
+    jitter: f32,
+    last_sample: Option<f32>,
+    probes: VecDeque<(u64, Instant)>,
+
+    window_start: Instant,
+    /// First sequence we expected in the current window
+    window_first: Option<Sequence>,
+    highest: Option<Sequence>,
+    received: u32,
+    bytes_in: usize,
+    bytes_out: usize,
+
+    // Results of the last complete window
+    loss: f32,
+    bytes_in_per_sec: f32,
+    bytes_out_per_sec: f32,
+}
+
+impl Default for NetStats {
+    fn default() -> Self {
+        NetStats {
+            next_sequence: 1,
+            rtt: None,
+            jitter: 0.0,
+            last_sample: None,
+            probes: VecDeque::new(),
+            window_start: Instant::now(),
+            window_first: None,
+            highest: None,
+            received: 0,
+            bytes_in: 0,
+            bytes_out: 0,
+            loss: 0.0,
+            bytes_in_per_sec: 0.0,
+            bytes_out_per_sec: 0.0,
+        }
+    }
+}
+
+impl NetStats {
+    /// Sequence number for the next outgoing packet, 0 is never used as it marks untracked packets
+    pub fn next_sequence(&mut self) -> Sequence {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1).max(1);
+        sequence
+    }
+
+    pub fn sent(&mut self, bytes: usize) {
+        self.roll();
+        self.bytes_out += bytes;
+    }
+
+    /// Counts an incoming packet, packets with sequence 0 only count towards the bytes
+    pub fn received(&mut self, bytes: usize, sequence: Sequence) {
+        self.roll();
+        self.bytes_in += bytes;
+
+        if sequence == 0 {
+            return;
+        }
+
+        if self.window_first.is_none() {
+            self.window_first = Some(sequence);
+        }
+        // Late packets from an earlier window still count, they weren't lost after all
+        if self.highest.is_none_or(|highest| sequence > highest) {
+            self.highest = Some(sequence);
+        }
+        self.received += 1;
+    }
+
+    /// Remembers when a probe was sent, an older probe with the same id is forgotten
+    pub fn sent_probe(&mut self, id: u64) {
+        self.probes.retain(|(probe, _)| *probe != id);
+        if self.probes.len() >= MAX_PROBES {
+            self.probes.pop_front();
+        }
+        self.probes.push_back((id, Instant::now()));
+    }
+
+    /// Takes a round trip sample if the probe is still waiting for an answer.
+    /// Probes sent before it are forgotten as their answers would arrive out of order.
+    pub fn answered_probe(&mut self, id: u64) {
+        let index = match self.probes.iter().position(|(probe, _)| *probe == id) {
+            Some(index) => index,
+            None => return,
+        };
+        let (_, sent_at) = self.probes.drain(..=index).next_back().unwrap();
+        let sample = sent_at.elapsed().as_secs_f32();
+
+        // Same smoothing as TCP and RTP use
+        self.rtt = Some(match self.rtt {
+            Some(rtt) => rtt + (sample - rtt) / 8.0,
+            None => sample,
+        });
+        if let Some(last) = self.last_sample {
+            self.jitter += ((sample - last).abs() - self.jitter) / 16.0;
+        }
+        self.last_sample = Some(sample);
+    }
+
+    pub fn summary(&mut self) -> NetStatsSummary {
+        self.roll();
+
+        NetStatsSummary {
+            rtt: self.rtt.map(Duration::from_secs_f32),
+            jitter: Duration::from_secs_f32(self.jitter),
+            loss: self.loss,
+            bytes_in_per_sec: self.bytes_in_per_sec,
+            bytes_out_per_sec: self.bytes_out_per_sec,
+        }
+    }
+
+    /// Closes the current window once it's old enough
+    fn roll(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < STATS_WINDOW {
+            return;
+        }
+
+        let seconds = elapsed.as_secs_f32();
+        self.bytes_in_per_sec = self.bytes_in as f32 / seconds;
+        self.bytes_out_per_sec = self.bytes_out as f32 / seconds;
+
+        if let (Some(first), Some(highest)) = (self.window_first, self.highest) {
+            // Nothing new arrived, which says nothing about loss when the other side had nothing to send
+            let expected = highest.wrapping_sub(first).wrapping_add(1);
+            if expected > 0 {
+                self.loss = 1.0 - (self.received as f32 / expected as f32).min(1.0);
+                self.window_first = Some(highest.wrapping_add(1));
+            }
+        }
+
+        self.window_start = Instant::now();
+        self.received = 0;
+        self.bytes_in = 0;
+        self.bytes_out = 0;
+    }
+}
+
+/// Point in time view of [NetStats]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetStatsSummary {
+    /// None until the first probe has been answered
+    pub rtt: Option<Duration>,
+    pub jitter: Duration,
+    /// Fraction of incoming packets lost, between 0 and 1
+    pub loss: f32,
+    pub bytes_in_per_sec: f32,
+    pub bytes_out_per_sec: f32,
+}
+
+impl NetStatsSummary {
+    pub fn ping_ms(&self) -> Option<u32> {
+        self.rtt.map(|rtt| rtt.as_millis() as u32)
+    }
+}
@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use message_io::network::Transport;
+
+/// Transports the game can run over, for networks that block some of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameTransport {
+    Udp,
+    /// TCP with every message framed, so messages keep their boundaries like datagrams do
+    Tcp,
+    /// WebSocket, which also gets through proxies that only allow HTTP
+    Ws,
+}
+
+impl GameTransport {
+    pub fn message_io(self) -> Transport {
+        match self {
+            GameTransport::Udp => Transport::Udp,
+            GameTransport::Tcp => Transport::FramedTcp,
+            GameTransport::Ws => Transport::Ws,
+        }
+    }
+
+    /// Port the transport runs on for a server hosted on `port`.
+    /// TCP and WebSocket can't share a port, so WebSocket is on the one after it, which the last port doesn't have.
+    pub fn port(self, port: u16) -> Result<u16, PortOutOfRange> {
+        match self {
+            GameTransport::Udp | GameTransport::Tcp => Ok(port),
+            GameTransport::Ws => port.checked_add(1).ok_or(PortOutOfRange(self, port)),
+        }
+    }
+}
+
+impl Display for GameTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameTransport::Udp => write!(f, "udp"),
+            GameTransport::Tcp => write!(f, "tcp"),
+            GameTransport::Ws => write!(f, "ws"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownTransport(String);
+
+impl Display for UnknownTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown transport {}, expected udp, tcp or ws", self.0)
+    }
+}
+
+impl Error for UnknownTransport {}
+
+/// A server hosted on this port has no room for the transport's port
+#[derive(Debug)]
+pub struct PortOutOfRange(GameTransport, u16);
+
+impl Display for PortOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} can't be used on port {}, it needs the port after it", self.0, self.1)
+    }
+}
+
+impl Error for PortOutOfRange {}
+
+impl FromStr for GameTransport {
+    type Err = UnknownTransport;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "udp" => Ok(GameTransport::Udp),
+            "tcp" => Ok(GameTransport::Tcp),
+            "ws" | "websocket" => Ok(GameTransport::Ws),
+            _ => Err(UnknownTransport(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn websocket_needs_the_next_port() {
+        assert_eq!(GameTransport::Ws.port(1337).unwrap(), 1338);
+        assert_eq!(GameTransport::Udp.port(u16::MAX).unwrap(), u16::MAX);
+        assert!(GameTransport::Ws.port(u16::MAX).is_err());
+    }
+}
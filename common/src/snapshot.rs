@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::components::EcsProtocol;
+use crate::Tick;
+
+/// The changes a client needs to apply to get from its `baseline` state to the state at `tick`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Server tick this snapshot describes
+    pub tick: Tick,
+
+    /// Tick of the acknowledged snapshot these changes are relative to.
+    /// `None` means the snapshot contains every value that is replicated through snapshots.
+    pub baseline: Option<Tick>,
+
+    pub changes: Vec<EcsProtocol>,
+}
+
+impl Snapshot {
+    pub fn is_full(&self) -> bool {
+        self.baseline.is_none()
+    }
+}
@@ -0,0 +1,28 @@
+use std::io;
+use std::net::IpAddr;
+
+use clap::Parser;
+use common::defaults::MASTER_SERVER_PORT;
+
+use crate::master::MasterServer;
+
+mod master;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Port to listen for game servers and clients on
+    #[arg(short, long, default_value_t = MASTER_SERVER_PORT)]
+    port: u16,
+
+    /// IP to listen on
+    #[arg(short, long, default_value = "0.0.0.0")]
+    ip: IpAddr,
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+
+    let mut master = MasterServer::bind((args.ip, args.port).into())?;
+    master.run()
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, ErrorKind};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::Instant;
+
+use chrono::Utc;
+use common::master::{
+    FromMasterMessage, Heartbeat, ListedServer, ToMasterMessage, LISTING_EXPIRY, LIST_RATE_WINDOW,
+    MAX_LISTINGS_PER_IP, MAX_LIST_REQUESTS, MAX_SERVER_NAME_LENGTH, SERVERS_PER_MESSAGE,
+};
+
+struct Listing {
+    server: ListedServer,
+    last_heartbeat: Instant,
+    /// Nonce the server answered the challenge with, every later heartbeat has to repeat it
+    nonce: u64,
+}
+
+/// A server that sent a heartbeat but hasn't repeated the nonce it was sent yet
+struct Challenge {
+    nonce: u64,
+    sent: Instant,
+}
+
+/// Requests an IP has made in the current window
+struct RequestWindow {
+    started: Instant,
+    requests: u32,
+}
+
+/// Keeps track of the game servers sending heartbeats and hands the list out to clients
+pub struct MasterServer {
+    socket: UdpSocket,
+    listings: HashMap<SocketAddr, Listing>,
+    /// Servers waiting to be listed, by the address they want to be listed at
+    challenges: HashMap<SocketAddr, Challenge>,
+    /// Requests are easy to spoof, without a limit the master server could be used to flood someone
+    requests: HashMap<IpAddr, RequestWindow>,
+}
+
+impl MasterServer {
+    pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        // Wakes up regularly even when nobody sends anything, so expired servers get delisted
+        socket.set_read_timeout(Some(LISTING_EXPIRY / 2))?;
+
+        log(format!("Master server listening on {addr}"));
+
+        Ok(MasterServer {
+            socket,
+            listings: HashMap::new(),
+            challenges: HashMap::new(),
+            requests: HashMap::new(),
+        })
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut buffer = [0; 2048];
+
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, from)) => match bincode::deserialize(&buffer[..len]) {
+                    Ok(message) => self.handle(message, from),
+                    Err(_) => log(format!("Warning: Invalid message from {from}")),
+                },
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                // Windows reports an earlier send failing this way, it doesn't concern the socket itself
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => {}
+                Err(err) => return Err(err),
+            }
+
+            self.expire();
+        }
+    }
+
+    fn handle(&mut self, message: ToMasterMessage, from: SocketAddr) {
+        match message {
+            ToMasterMessage::Heartbeat(heartbeat) => self.heartbeat(heartbeat, from),
+            ToMasterMessage::Unregister { port, nonce } => {
+                let addr = SocketAddr::new(from.ip(), port);
+                if self.listings.get(&addr).is_some_and(|listing| listing.nonce == nonce) {
+                    self.listings.remove(&addr);
+                    log(format!("Delisted {addr}, it shut down"));
+                }
+            }
+            ToMasterMessage::List { page } => {
+                if !self.allow_request(from.ip()) {
+                    return;
+                }
+                if let Err(err) = self.send_list(page, from) {
+                    log(format!("Warning: Failed to send the server list to {from}: {err}"));
+                }
+            }
+        }
+    }
+
+    fn heartbeat(&mut self, heartbeat: Heartbeat, from: SocketAddr) {
+        let addr = SocketAddr::new(from.ip(), heartbeat.port);
+
+        let nonce = match self.listings.get(&addr) {
+            Some(listing) => Some(listing.nonce),
+            None => self.challenges.get(&addr).map(|challenge| challenge.nonce),
+        };
+        let nonce = match (nonce, heartbeat.nonce) {
+            (Some(nonce), Some(answer)) if nonce == answer => nonce,
+            _ => {
+                self.challenge(addr, nonce, from);
+                return;
+            }
+        };
+        self.challenges.remove(&addr);
+
+        let server = ListedServer {
+            addr,
+            name: heartbeat.name.chars().take(MAX_SERVER_NAME_LENGTH).collect(),
+            players: heartbeat.players,
+            max_players: heartbeat.max_players,
+            protocol_version: heartbeat.protocol_version,
+        };
+
+        let listing = Listing {
+            server,
+            last_heartbeat: Instant::now(),
+            nonce,
+        };
+        if self.listings.insert(addr, listing).is_none() {
+            log(format!("Listed {addr}"));
+        }
+    }
+
+    /// Sends the nonce a server has to repeat to get listed, making one up if it doesn't have one yet
+    fn challenge(&mut self, addr: SocketAddr, nonce: Option<u64>, to: SocketAddr) {
+        // Answering spoofed heartbeats would make the master server flood someone else just the same
+        if !self.allow_request(to.ip()) {
+            return;
+        }
+
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => {
+                let listed = self
+                    .listings
+                    .keys()
+                    .chain(self.challenges.keys())
+                    .filter(|listed| listed.ip() == addr.ip())
+                    .count();
+                if listed >= MAX_LISTINGS_PER_IP {
+                    log(format!("Warning: Didn't list {addr}, its IP already has {listed} servers listed"));
+                    return;
+                }
+
+                let nonce = rand::random();
+                self.challenges.insert(addr, Challenge { nonce, sent: Instant::now() });
+                nonce
+            }
+        };
+
+        let message = FromMasterMessage::Challenge { port: addr.port(), nonce };
+        let data = bincode::serialize(&message).expect("Challenges should always be serializable");
+        if let Err(err) = self.socket.send_to(&data, to) {
+            log(format!("Warning: Failed to send a challenge to {to}: {err}"));
+        }
+    }
+
+    /// Counts a request that gets answered, false if the IP has made too many lately
+    fn allow_request(&mut self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let window = self.requests.entry(ip).or_insert(RequestWindow {
+            started: now,
+            requests: 0,
+        });
+        if now.duration_since(window.started) >= LIST_RATE_WINDOW {
+            window.started = now;
+            window.requests = 0;
+        }
+
+        window.requests += 1;
+        window.requests <= MAX_LIST_REQUESTS
+    }
+
+    fn send_list(&self, page: u32, to: SocketAddr) -> io::Result<()> {
+        let mut servers: Vec<&ListedServer> = self.listings.values().map(|listing| &listing.server).collect();
+        // Keeps the pages in the same order between requests
+        servers.sort_unstable_by_key(|server| server.addr);
+
+        // An empty list still gets a page, so the client knows there's nothing
+        let pages = servers.len().div_ceil(SERVERS_PER_MESSAGE).max(1);
+        let message = FromMasterMessage::ServerList {
+            page,
+            pages: pages as u32,
+            servers: servers
+                .chunks(SERVERS_PER_MESSAGE)
+                .nth(page as usize)
+                .unwrap_or_default()
+                .iter()
+                .map(|&server| server.clone())
+                .collect(),
+        };
+
+        let data = bincode::serialize(&message).expect("Server lists should always be serializable");
+        self.socket.send_to(&data, to)?;
+
+        Ok(())
+    }
+
+    fn expire(&mut self) {
+        self.requests
+            .retain(|_, window| window.started.elapsed() < LIST_RATE_WINDOW);
+        self.challenges
+            .retain(|_, challenge| challenge.sent.elapsed() < LISTING_EXPIRY);
+
+        self.listings.retain(|addr, listing| {
+            let alive = listing.last_heartbeat.elapsed() < LISTING_EXPIRY;
+            if !alive {
+                log(format!("Delisted {addr}, it stopped sending heartbeats"));
+            }
+            alive
+        });
+    }
+}
+
+fn log<T: Display>(message: T) {
+    let datetime = Utc::now().format("%Y-%m-%d %H:%M:%S");
+    println!("[{datetime}]: {message}");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use common::master::HEARTBEAT_INTERVAL;
+
+    use super::*;
+
+    fn master() -> MasterServer {
+        MasterServer::bind(([127, 0, 0, 1], 0).into()).unwrap()
+    }
+
+    /// Socket standing in for a game server or client, talking to the master server from localhost
+    fn peer() -> UdpSocket {
+        let socket = UdpSocket::bind(("127.0.0.1", 0)).unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        socket
+    }
+
+    fn receive(socket: &UdpSocket) -> FromMasterMessage {
+        let mut buffer = [0; 2048];
+        let (len, _) = socket.recv_from(&mut buffer).unwrap();
+        bincode::deserialize(&buffer[..len]).unwrap()
+    }
+
+    fn heartbeat(port: u16, nonce: Option<u64>) -> ToMasterMessage {
+        ToMasterMessage::Heartbeat(Heartbeat {
+            name: format!("Server {port}"),
+            port,
+            players: 0,
+            max_players: 16,
+            protocol_version: 0,
+            nonce,
+        })
+    }
+
+    /// Lists a server the way a game server would, answering the challenge it gets
+    fn list(master: &mut MasterServer, server: &UdpSocket, port: u16) -> u64 {
+        let from = server.local_addr().unwrap();
+        master.handle(heartbeat(port, None), from);
+        let nonce = match receive(server) {
+            FromMasterMessage::Challenge { nonce, .. } => nonce,
+            message => panic!("Expected a challenge, got {message:?}"),
+        };
+        master.handle(heartbeat(port, Some(nonce)), from);
+        nonce
+    }
+
+    #[test]
+    fn servers_are_listed_once_they_answer_the_challenge() {
+        let mut master = master();
+        let server = peer();
+        let from = server.local_addr().unwrap();
+
+        master.handle(heartbeat(7000, None), from);
+        assert!(master.listings.is_empty());
+        let nonce = match receive(&server) {
+            FromMasterMessage::Challenge { port, nonce } => {
+                assert_eq!(port, 7000);
+                nonce
+            }
+            message => panic!("Expected a challenge, got {message:?}"),
+        };
+
+        master.handle(heartbeat(7000, Some(nonce.wrapping_add(1))), from);
+        assert!(master.listings.is_empty());
+        // Still the same challenge, the first answer might just have been lost
+        assert!(matches!(receive(&server), FromMasterMessage::Challenge { nonce: resent, .. } if resent == nonce));
+
+        master.handle(heartbeat(7000, Some(nonce)), from);
+        let addr = SocketAddr::new(from.ip(), 7000);
+        assert!(master.listings.contains_key(&addr));
+        assert!(master.challenges.is_empty());
+    }
+
+    #[test]
+    fn unregister_needs_the_nonce() {
+        let mut master = master();
+        let server = peer();
+        let from = server.local_addr().unwrap();
+        let nonce = list(&mut master, &server, 7000);
+
+        let forged = ToMasterMessage::Unregister { port: 7000, nonce: nonce.wrapping_add(1) };
+        master.handle(forged, from);
+        assert_eq!(master.listings.len(), 1);
+
+        master.handle(ToMasterMessage::Unregister { port: 7000, nonce }, from);
+        assert!(master.listings.is_empty());
+    }
+
+    #[test]
+    fn listings_per_ip_are_capped() {
+        let mut master = master();
+        let server = peer();
+        for port in 0..MAX_LISTINGS_PER_IP as u16 {
+            list(&mut master, &server, 7000 + port);
+        }
+
+        master.handle(heartbeat(8000, None), server.local_addr().unwrap());
+        assert_eq!(master.listings.len(), MAX_LISTINGS_PER_IP);
+        assert!(master.challenges.is_empty());
+    }
+
+    #[test]
+    fn list_is_paged() {
+        let mut master = master();
+        let client = peer();
+        let count = SERVERS_PER_MESSAGE + 2;
+        // Filled in directly, a single IP can't list this many servers
+        for port in 0..count as u16 {
+            let addr = SocketAddr::new([127, 0, 0, 1].into(), 7000 + port);
+            let server = ListedServer {
+                addr,
+                name: String::new(),
+                players: 0,
+                max_players: 16,
+                protocol_version: 0,
+            };
+            let listing = Listing {
+                server,
+                last_heartbeat: Instant::now(),
+                nonce: 0,
+            };
+            master.listings.insert(addr, listing);
+        }
+
+        let mut listed = Vec::new();
+        for requested in 0..3 {
+            master.handle(ToMasterMessage::List { page: requested }, client.local_addr().unwrap());
+            match receive(&client) {
+                FromMasterMessage::ServerList { page, pages, servers } => {
+                    assert_eq!(page, requested);
+                    assert_eq!(pages, 2);
+                    listed.extend(servers.into_iter().map(|server| server.addr));
+                }
+                message => panic!("Expected a server list, got {message:?}"),
+            }
+        }
+
+        // Every server shows up exactly once, pages past the end are empty
+        listed.sort_unstable();
+        listed.dedup();
+        assert_eq!(listed.len(), count);
+    }
+
+    #[test]
+    fn empty_list_has_a_page() {
+        let mut master = master();
+        let client = peer();
+
+        master.handle(ToMasterMessage::List { page: 0 }, client.local_addr().unwrap());
+        match receive(&client) {
+            FromMasterMessage::ServerList { pages, servers, .. } => {
+                assert_eq!(pages, 1);
+                assert!(servers.is_empty());
+            }
+            message => panic!("Expected a server list, got {message:?}"),
+        }
+    }
+
+    #[test]
+    fn silent_servers_expire() {
+        let mut master = master();
+        let server = peer();
+        list(&mut master, &server, 7000);
+        list(&mut master, &server, 7001);
+
+        let silent = SocketAddr::new(server.local_addr().unwrap().ip(), 7000);
+        master.listings.get_mut(&silent).unwrap().last_heartbeat = Instant::now() - LISTING_EXPIRY;
+        master.expire();
+        assert_eq!(master.listings.len(), 1);
+        assert!(!master.listings.contains_key(&silent));
+
+        // Regular heartbeats keep a server listed
+        for listing in master.listings.values_mut() {
+            listing.last_heartbeat = Instant::now() - HEARTBEAT_INTERVAL;
+        }
+        master.expire();
+        assert_eq!(master.listings.len(), 1);
+    }
+}
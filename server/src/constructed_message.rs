@@ -1,28 +1,91 @@
+use std::{error::Error, fmt::Display};
+
 use message_io::network::Endpoint;
-use message_io::node::NodeHandler;
+use common::net::fragment::FragmentError;
+use common::net::simulator::Network;
+use common::net::stats::NetStats;
+use common::net::{codec, compression, fragment, Packet};
 use common::{FromServerMessage, Signal};
-use crate::server::RegisteredClients;
+use crate::server::{RegisteredClient, RegisteredClients};
 
-pub struct ConstructedMessage(Vec<u8>);
+/// A message already split into the packets that carry it
+pub struct ConstructedMessage(Vec<Packet>);
 
 impl ConstructedMessage {
-    pub fn send(&self, handler: &NodeHandler<Signal>, endpoint: Endpoint) {
-        handler.network().send(endpoint, &self.0);
+    /// Sends the message over the unreliable channel to an endpoint that isn't registered, so nothing is tracked.
+    /// Large messages are fragmented, losing any one fragment loses the whole message.
+    pub fn send(&self, network: &Network<Signal>, endpoint: Endpoint) {
+        for packet in &self.0 {
+            send_packet(network, endpoint, packet);
+        }
+    }
+
+    /// Sends the message over the unreliable channel and counts it towards the client's stats
+    pub fn send_to(&self, network: &Network<Signal>, endpoint: Endpoint, client: &mut RegisteredClient) {
+        for packet in &self.0 {
+            send_tracked(network, endpoint, packet.clone(), &mut client.stats);
+        }
+    }
+
+    /// Sends the message over the reliable channel, it will be resent until the client acknowledges it.
+    /// Large messages are fragmented and every fragment is resent separately.
+    pub fn send_reliable(&self, network: &Network<Signal>, endpoint: Endpoint, client: &mut RegisteredClient) {
+        for packet in &self.0 {
+            let packet = client.reliable.send(packet.clone());
+            send_tracked(network, endpoint, packet, &mut client.stats);
+        }
+    }
+
+    pub fn send_all(&self, network: &Network<Signal>, registered_clients: &mut RegisteredClients) {
+        for (&endpoint, client) in registered_clients.iter_mut() {
+            self.send_to(network, endpoint, client)
+        }
     }
+}
+
+/// Encodes a single [Packet] and sends it as is, returns the number of bytes sent
+pub fn send_packet(network: &Network<Signal>, endpoint: Endpoint, packet: &Packet) -> usize {
+    let data = codec::encode(packet);
+    network.send(endpoint, &data);
+    data.len()
+}
 
-    pub fn send_all(&self, handler: &NodeHandler<Signal>, registered_clients: &RegisteredClients) {
-        for &endpoint in registered_clients.keys() {
-            self.send(handler, endpoint)
+/// Stamps the packet with the next sequence number so the receiver can measure loss, then sends it
+pub fn send_tracked(network: &Network<Signal>, endpoint: Endpoint, mut packet: Packet, stats: &mut NetStats) {
+    packet.sequence = stats.next_sequence();
+    let bytes = send_packet(network, endpoint, &packet);
+    stats.sent(bytes);
+}
+
+#[derive(Debug)]
+pub enum ConstructError {
+    /// Encoding always works, but the result can still be too large to send
+    Fragment(FragmentError),
+}
+
+impl From<FragmentError> for ConstructError {
+    fn from(value: FragmentError) -> Self {
+        ConstructError::Fragment(value)
+    }
+}
+
+impl Display for ConstructError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstructError::Fragment(e) => write!(f, "{e}"),
         }
     }
 }
 
+impl Error for ConstructError {}
+
 pub trait ConstructMessage {
-    fn construct(&self) -> Result<ConstructedMessage, bincode::Error>;
+    fn construct(&self) -> Result<ConstructedMessage, ConstructError>;
 }
 
 impl ConstructMessage for FromServerMessage {
-    fn construct(&self) -> Result<ConstructedMessage, bincode::Error> {
-        Ok(ConstructedMessage(bincode::serialize(self)?))
+    fn construct(&self) -> Result<ConstructedMessage, ConstructError> {
+        let packets = fragment::split(compression::pack(codec::encode(self)))?;
+        Ok(ConstructedMessage(packets))
     }
 }
@@ -1,6 +1,19 @@
 // Server-only components go here
 
-use common::UserID;
+use std::collections::VecDeque;
+
+use common::ecs::components::InputState;
+use common::net::Sequence;
+use common::{Tick, UserID};
+
+/// An input waiting in the [InputQueue]
+#[derive(Debug, Clone, Copy)]
+pub struct QueuedInput {
+    pub sequence: Sequence,
+    pub input: InputState,
+    /// Tick the client rendered remote players at when it made this input
+    pub view_tick: Tick,
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Speed (pub f32);
@@ -10,6 +23,25 @@ pub struct ShotBy {
     pub id: Option<UserID>
 }
 
+/// Inputs that arrived but haven't been simulated yet, oldest first.
+/// One is applied every tick, the same way the client predicted it.
+#[derive(Debug, Clone, Default)]
+pub struct InputQueue(pub VecDeque<QueuedInput>);
+
+/// Tick the player's client rendered remote players at for the input being simulated, i.e. the world they are looking at
+#[derive(Debug, Clone, Copy)]
+pub struct SeenTick (pub Tick);
+
+/// Marks the entity of a client that's only watching, it has nothing else so it's never replicated
+#[derive(Debug, Clone)]
+pub struct Spectator {
+    pub name: String,
+}
+
+/// How many ticks in the past this bullet's hits are evaluated, to match what the shooter saw
+#[derive(Debug, Clone, Copy)]
+pub struct Rewind (pub Tick);
+
 // Timer specifiers
 pub struct ShootCooldown;
 pub struct BulletDespawn;
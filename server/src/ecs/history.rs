@@ -0,0 +1,47 @@
+use std::collections::{HashMap, VecDeque};
+
+use common::defaults::TICKS_PER_SECOND;
+use common::Tick;
+use glam::Vec2;
+use hecs::Entity;
+
+/// How far back hits are allowed to be evaluated.
+/// Without a cap, a client with a terrible connection could hit players who have been behind cover for ages.
+pub const MAX_REWIND_TICKS: Tick = TICKS_PER_SECOND / 5;
+
+/// The tick the server is currently simulating
+#[derive(Debug, Clone, Copy)]
+pub struct ServerTick(pub Tick);
+
+/// Rolling history of player positions, used to see the world the way a lagging client saw it
+#[derive(Debug, Default)]
+pub struct PositionHistory {
+    ticks: VecDeque<(Tick, HashMap<Entity, Vec2>)>,
+}
+
+impl PositionHistory {
+    pub fn record(&mut self, tick: Tick, positions: HashMap<Entity, Vec2>) {
+        self.ticks.push_back((tick, positions));
+
+        // Nothing older than the rewind cap will ever be looked at
+        while self.ticks.len() as Tick > MAX_REWIND_TICKS + 1 {
+            self.ticks.pop_front();
+        }
+    }
+
+    /// Position of an entity at the given tick, if it's still in the history
+    pub fn position_at(&self, tick: Tick, entity: Entity) -> Option<Vec2> {
+        let (oldest, _) = self.ticks.front()?;
+        let index = tick.checked_sub(*oldest)? as usize;
+
+        match self.ticks.get(index) {
+            Some((recorded, positions)) if *recorded == tick => positions.get(&entity).copied(),
+            // Ticks should be contiguous, but fall back to a search if they aren't
+            _ => self
+                .ticks
+                .iter()
+                .find(|(recorded, _)| *recorded == tick)
+                .and_then(|(_, positions)| positions.get(&entity).copied()),
+        }
+    }
+}
@@ -1,11 +1,13 @@
-use common::ecs::components::{EcsProtocol, InsertComponent};
-use hecs::World;
+use common::ecs::components::{EcsProtocol, InsertComponent, Player};
+use hecs::{Entity, NoSuchEntity, World};
 use resources::Resources;
 
+use crate::ecs::components::Spectator;
 use crate::ecs::observer::{ObservedWorld, Observer};
 use crate::ecs::systems::ServerSystems;
 
 pub mod components;
+pub mod history;
 pub mod observer;
 pub mod systems;
 pub mod spawn;
@@ -32,4 +34,26 @@ impl ServerEcs {
     pub fn init_client(&mut self) -> Vec<EcsProtocol> {
         InsertComponent::query_all(&mut self.world)
     }
+
+    pub fn is_spectator(&self, entity: Entity) -> bool {
+        self.world.get::<&Spectator>(entity).is_ok()
+    }
+
+    /// Name of a client's entity, whether it's playing or spectating
+    pub fn participant_name(&self, entity: Entity) -> Option<String> {
+        if let Ok(player) = self.world.get::<&Player>(entity) {
+            return Some(player.name.clone());
+        }
+        self.world.get::<&Spectator>(entity).ok().map(|spectator| spectator.name.clone())
+    }
+
+    /// Despawns the entity of a client that left.
+    /// Clients never heard of spectators, so those are despawned without telling anyone.
+    pub fn despawn_participant(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
+        if self.is_spectator(entity) {
+            self.world.despawn(entity)
+        } else {
+            self.observed_world().despawn(entity)
+        }
+    }
 }
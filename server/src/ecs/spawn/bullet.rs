@@ -6,11 +6,11 @@ use rand::{Rng, thread_rng};
 use common::ecs::components::{Velocity};
 use common::ecs::timer::Timer;
 use common::gun::Gun;
-use crate::ecs::components::{BulletDespawn};
+use crate::ecs::components::{BulletDespawn, Rewind};
 
 use crate::ecs::ServerEcs;
 
-pub fn spawn_bullet(ecs: &mut ServerEcs, player: Player, pos: Position, dir: LookDirection, gun: Gun) {
+pub fn spawn_bullet(ecs: &mut ServerEcs, player: Player, pos: Position, dir: LookDirection, gun: Gun, rewind: Rewind) {
     assert!(dir.0.is_normalized());
     let mut rng = thread_rng();
 
@@ -33,6 +33,7 @@ pub fn spawn_bullet(ecs: &mut ServerEcs, player: Player, pos: Position, dir: Loo
         // Insert server-side components
         ecs.world.insert(entity, (
             Timer::new(Duration::from_secs_f32(gun.range() / gun.bullet_speed()), BulletDespawn),
+            rewind,
         )).unwrap();
     }
 }
@@ -1,3 +1,4 @@
 pub mod player;
 pub mod bullet;
-pub mod weapon_crate;
\ No newline at end of file
+pub mod weapon_crate;
+pub mod spectator;
\ No newline at end of file
@@ -1,8 +1,8 @@
-use crate::ecs::components::{ShotBy, Speed};
-use common::ecs::components::{InputState, Velocity, Kills, Deaths};
+use crate::ecs::components::{InputQueue, SeenTick, ShotBy, Speed};
+use common::ecs::components::{InputState, Velocity, Kills, Deaths, LastInput};
 use common::gun::Gun;
 use common::{
-    defaults::DEFAULT_PLAYER_HP,
+    defaults::{DEFAULT_PLAYER_HP, PLAYER_SPEED},
     ecs::components::{Health, LookDirection, Player, Position},
     map::Map,
 };
@@ -11,8 +11,6 @@ use hecs::Entity;
 
 use crate::ecs::ServerEcs;
 
-const DEFAULT_SPEED: f32 = 2.5;
-
 pub fn spawn_player_at(pos: Position, ecs: &mut ServerEcs, username: &str) -> Entity {
     let entity = ecs.world.reserve_entity();
     // Insert observed components
@@ -31,6 +29,7 @@ pub fn spawn_player_at(pos: Position, ecs: &mut ServerEcs, username: &str) -> En
                 Gun::Pistol.to_held_weapon(),
                 Kills(0),
                 Deaths(0),
+                LastInput(0),
             ),
         )
         .unwrap();
@@ -42,7 +41,9 @@ pub fn spawn_player_at(pos: Position, ecs: &mut ServerEcs, username: &str) -> En
                 id: None,
             },
             InputState::default(),
-            Speed(DEFAULT_SPEED),
+            InputQueue::default(),
+            Speed(PLAYER_SPEED),
+            SeenTick(0),
         ))
         .unwrap();
 
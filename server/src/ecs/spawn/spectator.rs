@@ -0,0 +1,11 @@
+use hecs::Entity;
+
+use crate::ecs::components::Spectator;
+use crate::ecs::ServerEcs;
+
+/// Spectators only exist on the server, so they're spawned without going through the observer
+pub fn spawn_spectator(ecs: &mut ServerEcs, username: &str) -> Entity {
+    ecs.world.spawn((Spectator {
+        name: username.to_string(),
+    },))
+}
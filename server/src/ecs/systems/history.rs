@@ -0,0 +1,18 @@
+use common::ecs::components::{Player, Position};
+use crate::ecs::history::{PositionHistory, ServerTick};
+use crate::ecs::ServerEcs;
+use crate::ecs::systems::ServerSystems;
+
+impl ServerSystems {
+    /// Remember where every player is at the end of this tick, for lag compensation
+    pub fn record_history_system(ecs: &mut ServerEcs, _dt: f32) {
+        let tick = ecs.resources.get::<ServerTick>().unwrap().0;
+
+        let positions = ecs.world.query_mut::<&Position>().with::<&Player>()
+            .into_iter()
+            .map(|(entity, pos)| (entity, pos.0))
+            .collect();
+
+        ecs.resources.get_mut::<PositionHistory>().unwrap().record(tick, positions);
+    }
+}
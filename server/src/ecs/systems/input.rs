@@ -1,51 +1,54 @@
-use glam::Vec2;
+use common::ecs::components::{InputState, LastInput, LookDirection, Position, Velocity};
+use common::ecs::system::movement;
+use common::map::Map;
 
-use common::ecs::components::{InputState, LookDirection, Velocity};
-
-use crate::ecs::components::Speed;
+use crate::ecs::components::{InputQueue, QueuedInput, SeenTick, Speed};
 use crate::ecs::ServerEcs;
 use crate::ecs::systems::ServerSystems;
 
 impl ServerSystems {
-    /// Applies input state to Velocity and LookDirection
+    /// Applies the oldest queued input of every player, moving them by exactly one tick of it.
+    /// Players without a new input stand still, the client hasn't predicted anything for this tick either.
     pub fn input_system(ecs: &mut ServerEcs, _dt: f32) {
-        let query = ecs.world.query_mut::<(&InputState, &mut Velocity, &mut LookDirection, &Speed)>();
-
-        for (entity, (input, vel, look_dir, speed)) in query {
-            // Apply look_direction
-            // Using a block so look_dir gets dropped before observing vel
+        let map = ecs.resources.get::<Map>().unwrap();
+        let query = ecs.world.query_mut::<(
+            &mut InputQueue,
+            &mut InputState,
+            &mut Position,
+            &mut Velocity,
+            &mut LookDirection,
+            &mut LastInput,
+            &mut SeenTick,
+            &Speed,
+        )>();
+
+        for (entity, (queue, input, pos, vel, look_dir, last_input, seen_tick, speed)) in query {
+            let QueuedInput { sequence, input: next, view_tick } = match queue.0.pop_front() {
+                Some(next) => next,
+                None => continue,
+            };
+            // Other systems like shooting keep reading the newest applied input and what the client saw with it
+            *input = next;
+            seen_tick.0 = view_tick;
+
+            // Using blocks so each observed component gets dropped before observing the next
             {
-                let mut look_dir = ecs.observer.observe_component(entity, look_dir);
-                look_dir.0 = Vec2::from_angle(input.look_angle);
+                let mut look_dir = ecs.observer.observe_component(entity, look_dir).unreliable();
+                look_dir.0 = movement::look_direction(input);
             }
-
-            // Apply velocity
-            let mut move_dir = Vec2::ZERO;
-
-            let forward = look_dir.0;
-            let right = forward.perp();
-
-            if input.forward {
-                move_dir += forward;
-            }
-
-            if input.backward {
-                move_dir -= forward;
-            }
-
-            if input.right {
-                move_dir += right;
+            {
+                let mut pos = ecs.observer.observe_component(entity, pos).unreliable();
+                pos.0 = movement::step(&map, pos.0, input, speed.0);
             }
-
-            if input.left {
-                move_dir -= right;
+            {
+                // Not used for moving players anymore, but clients animate with it
+                let mut vel = ecs.observer.observe_component(entity, vel).unreliable();
+                vel.0 = movement::velocity(input, speed.0);
             }
 
-            // this makes backend and frontend crash, so I commented it out for now -Jacob
-            move_dir = move_dir.normalize_or_zero();
-
-            let mut vel = ecs.observer.observe_component(entity, vel);
-            vel.0 = move_dir * speed.0;
+            // Only now that it's applied, the position the client gets back includes this input
+            let mut last_input = ecs.observer.observe_component(entity, last_input).unreliable();
+            last_input.0 = sequence;
         }
     }
 }
@@ -7,6 +7,7 @@ mod pick_up;
 mod collisions;
 mod respawn;
 mod ammo;
+mod history;
 
 /// Server-side systems are implemented onto this
 pub struct ServerSystems;
@@ -22,5 +23,6 @@ impl ServerSystems {
         ServerSystems::respawn_system(ecs, dt);
         ServerSystems::collision_system(ecs, dt);
         ServerSystems::reset_to_pistol(ecs, dt);
+        ServerSystems::record_history_system(ecs, dt);
     }
 }
@@ -1,15 +1,15 @@
 use crate::ecs::ServerEcs;
 use crate::{ecs::systems::ServerSystems};
-use common::ecs::components::{Position, Velocity};
+use common::ecs::components::{Player, Position, Velocity};
 
 impl ServerSystems {
-    /// Move all entities with a position and velocity
+    /// Move all entities with a position and velocity, except players who are moved by their inputs
     pub fn move_system(ecs: &mut ServerEcs, dt: f32) {
-        let query = ecs.world.query_mut::<(&Velocity, &mut Position)>();
+        let query = ecs.world.query_mut::<(&Velocity, &mut Position)>().without::<&Player>();
 
         for (entity, (vel, pos)) in query {
             // Observe the shared components we mutate
-            let mut pos = ecs.observer.observe_component(entity, pos);
+            let mut pos = ecs.observer.observe_component(entity, pos).unreliable();
 
             // Unwrap component inner types
             let pos = &mut pos.0;
@@ -27,7 +27,8 @@ impl ServerSystems {
                 death_positions.push(p.0);
                 killers.push(s_b.id);
 
-                let mut p = ecs.observer.observe_component(e, p);
+                // Teleporting is movement too, it shouldn't race with the unreliable position updates
+                let mut p = ecs.observer.observe_component(e, p).unreliable();
                 *p = ecs
                     .resources
                     .get::<Map>()
@@ -5,27 +5,31 @@ use crate::ecs::ServerEcs;
 use common::ecs::components::{HeldWeapon, InputState, LookDirection, Position, Player};
 use common::ecs::timer::Timer;
 use common::gun::Gun;
-use crate::ecs::components::{BulletDespawn, ShootCooldown};
+use crate::ecs::components::{BulletDespawn, Rewind, SeenTick, ShootCooldown};
+use crate::ecs::history::{ServerTick, MAX_REWIND_TICKS};
 
 struct BulletSpawn {
     player: Player,
     pos: Position,
     dir: LookDirection,
     gun: Gun,
+    rewind: Rewind,
 }
 
 impl ServerSystems {
     /// Applies input state to Velocity and LookDirection
     pub fn shoot_system(ecs: &mut ServerEcs, _dt: f32) {
+        let tick = ecs.resources.get::<ServerTick>().unwrap().0;
+
         let query = ecs
             .world
-            .query_mut::<(&Player, &InputState, &LookDirection, &Position, &mut HeldWeapon)>()
+            .query_mut::<(&Player, &InputState, &LookDirection, &Position, &mut HeldWeapon, &SeenTick)>()
             .without::<&Timer<ShootCooldown>>();
 
         let mut bullets = Vec::new();
         let mut cooldowns = Vec::new();
 
-        for (entity, (player, input, look_dir, position, weapon)) in query {
+        for (entity, (player, input, look_dir, position, weapon, seen_tick)) in query {
             let mut weapon = ecs.observer.observe_component(entity, weapon);
 
             // Check if shooting
@@ -39,6 +43,8 @@ impl ServerSystems {
                 pos: Position(Vec2::new(position.0.x + (look_dir.0.x * 0.4), position.0.y + (look_dir.0.y * 0.4))),
                 dir: *look_dir,
                 gun: weapon.gun,
+                // Hits are checked against the world the shooter was looking at
+                rewind: Rewind(tick.saturating_sub(seen_tick.0).min(MAX_REWIND_TICKS)),
             });
 
             // Subtract ammo
@@ -50,7 +56,7 @@ impl ServerSystems {
         }
 
         for bullet in bullets {
-            spawn_bullet(ecs, bullet.player, bullet.pos, bullet.dir, bullet.gun);
+            spawn_bullet(ecs, bullet.player, bullet.pos, bullet.dir, bullet.gun, bullet.rewind);
         }
 
         for (entity, cooldown) in cooldowns {
@@ -0,0 +1,35 @@
+use std::{error::Error, fmt::Display};
+
+use common::net::Sequence;
+use message_io::network::Endpoint;
+
+use crate::server::Server;
+
+#[derive(Debug)]
+pub enum AckError {
+    FailedToGetClient,
+}
+
+impl Display for AckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AckError::FailedToGetClient => {
+                write!(f, "tried to acknowledge a reliable packet for unregistered client")
+            }
+        }
+    }
+}
+
+impl Error for AckError {}
+
+pub fn execute(server: &mut Server, sequence: Sequence, endpoint: Endpoint) -> Result<(), AckError> {
+    let client = server
+        .registered_clients
+        .get_mut(&endpoint)
+        .ok_or(AckError::FailedToGetClient)?;
+
+    // Duplicate acks are expected, the client acks every copy of a resent packet it gets
+    client.reliable.ack(sequence);
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::{error::Error, fmt::Display};
+
+use common::Tick;
+use message_io::network::Endpoint;
+
+use crate::server::Server;
+
+#[derive(Debug)]
+pub enum AckError {
+    FailedToGetClient,
+    FutureTick(Tick),
+}
+
+impl Display for AckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AckError::FailedToGetClient => {
+                write!(f, "tried to acknowledge a snapshot for unregistered client")
+            }
+            AckError::FutureTick(tick) => {
+                write!(f, "client acknowledged snapshot {tick} which hasn't been sent yet")
+            }
+        }
+    }
+}
+
+impl Error for AckError {}
+
+pub fn execute(server: &mut Server, tick: Tick, endpoint: Endpoint) -> Result<(), AckError> {
+    if tick > server.tick {
+        return Err(AckError::FutureTick(tick));
+    }
+
+    let client = server
+        .registered_clients
+        .get_mut(&endpoint)
+        .ok_or(AckError::FailedToGetClient)?;
+    client.stats.answered_probe(tick);
+
+    // Acks can arrive out of order, only ever move the baseline forward.
+    // Baselines the server no longer has history for are useless, the client will get a full snapshot instead.
+    if client.acked_tick.map_or(true, |acked| tick > acked) && server.snapshots.is_valid_baseline(tick) {
+        client.acked_tick = Some(tick);
+    }
+
+    Ok(())
+}
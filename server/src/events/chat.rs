@@ -0,0 +1,80 @@
+use std::{error::Error, fmt::Display};
+
+use common::{ChatMessage, FromServerMessage};
+use message_io::network::Endpoint;
+
+use crate::constructed_message::{ConstructError, ConstructMessage};
+use crate::events::validation::{self, ValidationError};
+use crate::server::{Logger, Server};
+
+#[derive(Debug)]
+pub enum ChatError {
+    FailedToGetPlayer,
+    FailedToFindPlayerInEcs,
+    Invalid(ValidationError),
+    Construct(ConstructError),
+}
+
+impl From<ValidationError> for ChatError {
+    fn from(value: ValidationError) -> Self {
+        ChatError::Invalid(value)
+    }
+}
+
+impl From<ConstructError> for ChatError {
+    fn from(value: ConstructError) -> Self {
+        ChatError::Construct(value)
+    }
+}
+
+impl Display for ChatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChatError::FailedToGetPlayer => write!(f, "unregistered client tried to chat"),
+            ChatError::FailedToFindPlayerInEcs => {
+                write!(f, "client is registered but not found in ecs")
+            }
+            ChatError::Invalid(e) => write!(f, "rejected chat message: {e}"),
+            ChatError::Construct(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for ChatError {}
+
+/// Sends the message to every player and spectator, including the one who sent it so they know it went through
+pub fn execute(server: &mut Server, endpoint: Endpoint, text: &str) -> Result<(), ChatError> {
+    let text = validation::chat_message(text)?;
+
+    let entity = server
+        .registered_clients
+        .get(&endpoint)
+        .ok_or(ChatError::FailedToGetPlayer)?
+        .entity;
+
+    // Spectators can chat too, their ids are made from their entity the same way as a player's
+    let name = server
+        .ecs
+        .participant_name(entity)
+        .ok_or(ChatError::FailedToFindPlayerInEcs)?;
+
+    server
+        .ecs
+        .resources
+        .get::<Logger>()
+        .unwrap()
+        .log(format!("[{name}] {text}"));
+
+    let message = FromServerMessage::Chat(ChatMessage {
+        id: entity.to_bits().into(),
+        name,
+        text,
+    })
+    .construct()?;
+
+    for (&endpoint, client) in server.registered_clients.iter_mut() {
+        message.send_reliable(&server.network, endpoint, client);
+    }
+
+    Ok(())
+}
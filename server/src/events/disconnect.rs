@@ -0,0 +1,59 @@
+use std::{error::Error, fmt::Display};
+
+use common::{DisconnectReason, FromServerMessage};
+use message_io::network::Endpoint;
+
+use crate::constructed_message::{ConstructError, ConstructMessage};
+use crate::events::leave::{self, LeaveErrors};
+use crate::server::{Logger, Server};
+
+#[derive(Debug)]
+pub enum DisconnectError {
+    Construct(ConstructError),
+    Leave(LeaveErrors),
+}
+
+impl From<ConstructError> for DisconnectError {
+    fn from(value: ConstructError) -> Self {
+        DisconnectError::Construct(value)
+    }
+}
+
+impl From<LeaveErrors> for DisconnectError {
+    fn from(value: LeaveErrors) -> Self {
+        DisconnectError::Leave(value)
+    }
+}
+
+impl Display for DisconnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisconnectError::Construct(e) => write!(f, "DisconnectError: {e}"),
+            DisconnectError::Leave(e) => write!(f, "DisconnectError: {e}"),
+        }
+    }
+}
+
+impl Error for DisconnectError {}
+
+/// Tells a client why it's being removed, then removes it like it had left by itself.
+/// Also works for clients that aren't registered, e.g. to refuse a join.
+pub fn execute(
+    server: &mut Server,
+    endpoint: Endpoint,
+    reason: DisconnectReason,
+) -> Result<(), DisconnectError> {
+    server.ecs.resources.get::<Logger>().unwrap().log(format!(
+        "Disconnecting IP {}: {reason}",
+        endpoint.addr()
+    ));
+
+    // Sent once, if it gets lost the client hears its session expired the next time it sends anything
+    FromServerMessage::Disconnect(reason)
+        .construct()?
+        .send(&server.network, endpoint);
+
+    leave::execute(server, endpoint)?;
+
+    Ok(())
+}
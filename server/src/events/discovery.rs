@@ -0,0 +1,49 @@
+use std::{error::Error, fmt::Display};
+
+use common::discovery::{DiscoveryProbe, DiscoveryResponse};
+use common::handshake::PROTOCOL_VERSION;
+use message_io::network::Endpoint;
+
+use crate::server::Server;
+
+#[derive(Debug)]
+pub enum DiscoveryError {
+    InvalidProbe,
+    Bincode(bincode::Error),
+}
+
+impl Display for DiscoveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscoveryError::InvalidProbe => write!(f, "received an invalid discovery probe"),
+            DiscoveryError::Bincode(err) => write!(f, "failed to serialize discovery response: {err}"),
+        }
+    }
+}
+
+impl Error for DiscoveryError {}
+
+impl From<bincode::Error> for DiscoveryError {
+    fn from(value: bincode::Error) -> Self {
+        DiscoveryError::Bincode(value)
+    }
+}
+
+/// Answers a LAN discovery probe, it doesn't matter if the endpoint is registered or not
+pub fn execute(server: &mut Server, endpoint: Endpoint, data: &[u8]) -> Result<(), DiscoveryError> {
+    let probe: DiscoveryProbe = bincode::deserialize(data).map_err(|_| DiscoveryError::InvalidProbe)?;
+    if probe.protocol_version != PROTOCOL_VERSION {
+        return Ok(());
+    }
+
+    let response = DiscoveryResponse {
+        name: server.config.name.clone(),
+        players: server.player_count(),
+        max_players: server.config.max_players,
+        port: server.addr.port(),
+    };
+
+    server.network.send(endpoint, &bincode::serialize(&response)?);
+
+    Ok(())
+}
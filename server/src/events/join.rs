@@ -1,23 +1,41 @@
-use common::defaults::DEFAULT_PLAYER_NAME;
 use message_io::network::Endpoint;
 use std::{error::Error, fmt::Display};
 
-use common::{map::Map, FromServerMessage};
+use common::handshake::{JoinRejection, JoinRequest, BUILD, PROTOCOL_VERSION};
+use common::{map::Map, DisconnectReason, FromServerMessage};
 use resources::CantGetResource;
 
-use crate::constructed_message::ConstructMessage;
+use crate::constructed_message::{ConstructError, ConstructMessage};
 use crate::ecs::spawn::player::spawn_player;
-use crate::server::{Logger, Server};
+use crate::ecs::spawn::spectator::spawn_spectator;
+use crate::events::disconnect::{self, DisconnectError};
+use crate::events::validation::{self, ValidationError};
+use crate::relevance::Spatial;
+use crate::server::{Logger, RegisteredClient, Server};
 
 #[derive(Debug)]
 pub enum JoinError {
     Resources(CantGetResource),
-    Serialize(bincode::Error),
+    Construct(ConstructError),
+    Disconnect(DisconnectError),
+    Invalid(ValidationError),
 }
 
-impl From<bincode::Error> for JoinError {
-    fn from(value: bincode::Error) -> Self {
-        JoinError::Serialize(value)
+impl From<ValidationError> for JoinError {
+    fn from(value: ValidationError) -> Self {
+        JoinError::Invalid(value)
+    }
+}
+
+impl From<DisconnectError> for JoinError {
+    fn from(value: DisconnectError) -> Self {
+        JoinError::Disconnect(value)
+    }
+}
+
+impl From<ConstructError> for JoinError {
+    fn from(value: ConstructError) -> Self {
+        JoinError::Construct(value)
     }
 }
 
@@ -33,14 +51,20 @@ impl Display for JoinError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             JoinError::Resources(e) => write!(f, "JoinError: {e}"),
-            JoinError::Serialize(e) => write!(f, "JoinError: {e}"),
+            JoinError::Construct(e) => write!(f, "JoinError: {e}"),
+            JoinError::Disconnect(e) => write!(f, "JoinError: {e}"),
+            JoinError::Invalid(e) => write!(f, "JoinError: {e}"),
         }
     }
 }
 // Registers user
-pub fn execute(server: &mut Server, endpoint: Endpoint, username: &str) -> Result<(), JoinError> {
+pub fn execute(server: &mut Server, endpoint: Endpoint, request: &JoinRequest) -> Result<(), JoinError> {
     let logger = server.ecs.resources.get::<Logger>().unwrap().clone();
 
+    if !check_version(server, endpoint, request)? {
+        return Ok(());
+    }
+
     if server.is_registered(endpoint) {
         logger.log(format!(
             "Participant with IP {} already exists",
@@ -49,37 +73,111 @@ pub fn execute(server: &mut Server, endpoint: Endpoint, username: &str) -> Resul
         return Ok(());
     }
 
-    let (_, entity) = spawn_player(
-        &mut server.ecs,
-        if username.is_empty() {
-            DEFAULT_PLAYER_NAME
-        } else {
-            username
+    if server.banned.contains(&endpoint.addr().ip()) {
+        disconnect::execute(server, endpoint, DisconnectReason::Banned(String::new()))?;
+        return Ok(());
+    }
+
+    let full = if request.spectate {
+        server.spectator_count() >= server.config.max_spectators
+    } else {
+        server.player_count() >= server.config.max_players
+    };
+    if full {
+        disconnect::execute(server, endpoint, DisconnectReason::ServerFull)?;
+        return Ok(());
+    }
+
+    let username = match validation::username(&request.username) {
+        Ok(username) => username,
+        Err(err) => {
+            reject(server, endpoint, JoinRejection::InvalidUsername(err.to_string()))?;
+            return Err(err.into());
+        }
+    };
+    let entity = if request.spectate {
+        logger.log(format!("Added spectator with ip {}", endpoint.addr()));
+        spawn_spectator(&mut server.ecs, &username)
+    } else {
+        logger.log(format!("Added participant with ip {}", endpoint.addr()));
+        spawn_player(&mut server.ecs, &username).1
+    };
+
+    // The initial ECS state sent below is the state of the current tick,
+    // so snapshots can be deltas against it right away
+    register(server, endpoint, RegisteredClient::new(entity, Some(server.tick)))
+}
+
+/// Rejects the request if the client speaks a different protocol version.
+/// Returns whether the client may continue.
+pub(crate) fn check_version(server: &mut Server, endpoint: Endpoint, request: &JoinRequest) -> Result<bool, JoinError> {
+    if request.protocol_version == PROTOCOL_VERSION {
+        return Ok(true);
+    }
+
+    server.ecs.resources.get::<Logger>().unwrap().log(format!(
+        "Rejected IP {} running version {} (build {})",
+        endpoint.addr(),
+        request.protocol_version,
+        request.build
+    ));
+
+    reject(
+        server,
+        endpoint,
+        JoinRejection::VersionMismatch {
+            server_version: PROTOCOL_VERSION,
+            server_build: BUILD.to_string(),
         },
-    );
+    )?;
+    Ok(false)
+}
 
-    FromServerMessage::OwnId(entity.to_bits().into())
+pub(crate) fn reject(server: &mut Server, endpoint: Endpoint, rejection: JoinRejection) -> Result<(), JoinError> {
+    // Not registered yet so there's no reliable channel, the client retries joining until it hears back
+    FromServerMessage::JoinResponse(Err(rejection))
         .construct()?
-        .send(&server.handler, endpoint);
-
-    // Add player to the server clients
-    logger.log(format!("Added participant with ip {}", endpoint.addr()));
+        .send(&server.network, endpoint);
+    Ok(())
+}
 
-    // Spawns player
+/// Sends everything a client needs to start playing or spectating and starts tracking it
+pub(crate) fn register(server: &mut Server, endpoint: Endpoint, mut client: RegisteredClient) -> Result<(), JoinError> {
+    FromServerMessage::JoinResponse(Ok(client.token))
+        .construct()?
+        .send_reliable(&server.network, endpoint, &mut client);
 
-    server.registered_clients.insert(endpoint, entity);
+    let spectator = server.ecs.is_spectator(client.entity);
+    if spectator {
+        FromServerMessage::Spectating
+    } else {
+        FromServerMessage::OwnId(client.entity.to_bits().into())
+    }
+    .construct()?
+    .send_reliable(&server.network, endpoint, &mut client);
 
     // Sending initial map to player
-    logger.log(format!("Sending map to IP {}", endpoint.addr()));
+    server.ecs.resources.get::<Logger>().unwrap().log(format!("Sending map to IP {}", endpoint.addr()));
 
     FromServerMessage::SendMap(server.ecs.resources.get::<Map>()?.clone())
         .construct()?
-        .send(&server.handler, endpoint);
+        .send_reliable(&server.network, endpoint, &mut client);
+
+    // Sends ECS history to the newly joined user, limited to what they can perceive
+    let spatial = Spatial::new(&mut server.ecs.world);
+    client.relevance.update(
+        server.tick,
+        Some(client.entity).filter(|_| !spectator),
+        &spatial,
+        &*server.ecs.resources.get::<Map>()?,
+    );
+    let init = client.relevance.filter(&server.ecs.init_client(), &spatial);
 
-    // Sends ECS history to the newly joined user
-    FromServerMessage::EcsChanges(server.ecs.init_client())
+    FromServerMessage::EcsChanges(init)
         .construct()?
-        .send(&server.handler, endpoint);
+        .send_reliable(&server.network, endpoint, &mut client);
+
+    server.registered_clients.insert(endpoint, client);
 
     Ok(())
 }
@@ -10,7 +10,6 @@ pub enum LeaveErrors {
     FailedToRemoveFromHashMap,
     FailedToFindPlayer,
     DeSpawn(NoSuchEntity),
-    Serialize(bincode::Error),
 }
 
 impl From<NoSuchEntity> for LeaveErrors {
@@ -19,12 +18,6 @@ impl From<NoSuchEntity> for LeaveErrors {
     }
 }
 
-impl From<bincode::Error> for LeaveErrors {
-    fn from(value: bincode::Error) -> Self {
-        LeaveErrors::Serialize(value)
-    }
-}
-
 impl Display for LeaveErrors {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -33,7 +26,6 @@ impl Display for LeaveErrors {
             }
             LeaveErrors::FailedToFindPlayer => write!(f, "Can't find player to despawn"),
             LeaveErrors::DeSpawn(e) => write!(f, "{e}"),
-            LeaveErrors::Serialize(e) => write!(f, "{e}"),
         }
     }
 }
@@ -45,9 +37,10 @@ pub fn execute(server: &mut Server, endpoint: Endpoint) -> Result<(), LeaveError
         let player_entity = server
             .registered_clients
             .remove(&endpoint)
-            .ok_or(LeaveErrors::FailedToRemoveFromHashMap)?;
+            .ok_or(LeaveErrors::FailedToRemoveFromHashMap)?
+            .entity;
 
-        server.ecs.observed_world().despawn(player_entity)?;
+        server.ecs.despawn_participant(player_entity)?;
 
         server.ecs.resources.get::<Logger>().unwrap().log(format!(
             "Unregistered participant with ip {}",
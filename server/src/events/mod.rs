@@ -1,4 +1,13 @@
+pub mod ack;
+pub mod ack_snapshot;
+pub mod chat;
+pub mod disconnect;
+pub mod discovery;
 pub mod join;
 pub mod leave;
 pub mod ping;
-pub mod update_inputs;
\ No newline at end of file
+pub mod play;
+pub mod query;
+pub mod resume;
+pub mod update_inputs;
+pub mod validation;
\ No newline at end of file
@@ -1,17 +1,22 @@
-use common::{FromServerMessage, Signal};
-use message_io::{network::Endpoint, node::NodeHandler};
-use crate::constructed_message::ConstructMessage;
+use common::FromServerMessage;
+use message_io::network::Endpoint;
+use crate::constructed_message::{ConstructError, ConstructMessage};
 
-use crate::server::Logger;
+use crate::server::{Logger, Server};
 
 pub fn execute(
+    server: &mut Server,
     logger: &Logger,
-    handler: &NodeHandler<Signal>,
     endpoint: Endpoint,
-) -> Result<(), bincode::Error> {
+) -> Result<(), ConstructError> {
     logger.log(format!("Ping from {}", endpoint.addr()));
 
-    FromServerMessage::Pong.construct()?.send(handler, endpoint);
+    // Clients ping before they've joined too, the pong just isn't counted towards their stats then
+    let pong = FromServerMessage::Pong.construct()?;
+    match server.registered_clients.get_mut(&endpoint) {
+        Some(client) => pong.send_to(&server.network, endpoint, client),
+        None => pong.send(&server.network, endpoint),
+    }
 
     Ok(())
 }
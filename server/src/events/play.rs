@@ -0,0 +1,79 @@
+use std::{error::Error, fmt::Display};
+
+use common::FromServerMessage;
+use hecs::ComponentError;
+use message_io::network::Endpoint;
+
+use crate::constructed_message::{ConstructError, ConstructMessage};
+use crate::ecs::components::Spectator;
+use crate::ecs::spawn::player::spawn_player;
+use crate::server::{Logger, Server};
+
+#[derive(Debug)]
+pub enum PlayError {
+    FailedToGetSpectator,
+    /// Only spectators can ask for a player
+    NotSpectating(ComponentError),
+    Construct(ConstructError),
+}
+
+impl From<ComponentError> for PlayError {
+    fn from(value: ComponentError) -> Self {
+        PlayError::NotSpectating(value)
+    }
+}
+
+impl From<ConstructError> for PlayError {
+    fn from(value: ConstructError) -> Self {
+        PlayError::Construct(value)
+    }
+}
+
+impl Display for PlayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayError::FailedToGetSpectator => write!(f, "unregistered client asked to play"),
+            PlayError::NotSpectating(e) => write!(f, "client asked to play without spectating: {e}"),
+            PlayError::Construct(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for PlayError {}
+
+/// Turns a spectator into a player if there's a free slot.
+/// The client follows its new player once it gets the OwnId.
+pub fn execute(server: &mut Server, endpoint: Endpoint) -> Result<(), PlayError> {
+    let spectator = server
+        .registered_clients
+        .get(&endpoint)
+        .ok_or(PlayError::FailedToGetSpectator)?
+        .entity;
+
+    let name = server.ecs.world.get::<&Spectator>(spectator)?.name.clone();
+
+    if server.player_count() >= server.config.max_players {
+        let client = server.registered_clients.get_mut(&endpoint).unwrap();
+        FromServerMessage::NoFreeSlot
+            .construct()?
+            .send_reliable(&server.network, endpoint, client);
+        return Ok(());
+    }
+
+    let (_, entity) = spawn_player(&mut server.ecs, &name);
+    server.ecs.despawn_participant(spectator).ok();
+
+    server.ecs.resources.get::<Logger>().unwrap().log(format!(
+        "Spectator {name} with ip {} started playing",
+        endpoint.addr()
+    ));
+
+    // The new player reaches the client through the reliable channel like any other spawn
+    let client = server.registered_clients.get_mut(&endpoint).unwrap();
+    client.entity = entity;
+    FromServerMessage::OwnId(entity.to_bits().into())
+        .construct()?
+        .send_reliable(&server.network, endpoint, client);
+
+    Ok(())
+}
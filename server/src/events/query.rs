@@ -0,0 +1,46 @@
+use common::discovery::{GameMode, ServerInfo};
+use common::ecs::components::Player;
+use common::handshake::PROTOCOL_VERSION;
+use common::map::Map;
+use common::FromServerMessage;
+use message_io::network::Endpoint;
+
+use crate::constructed_message::{ConstructError, ConstructMessage};
+use crate::server::Server;
+
+/// Describes the server to anyone who asks, nothing is kept about who asked
+pub fn execute(server: &mut Server, endpoint: Endpoint) -> Result<(), ConstructError> {
+    let (map_width, map_height) = match server.ecs.resources.get::<Map>() {
+        Ok(map) => (map.width, map.height),
+        Err(_) => (0, 0),
+    };
+
+    // Suspended players are listed too, they still hold their slot
+    let players = server
+        .participants()
+        .filter_map(|entity| {
+            server
+                .ecs
+                .world
+                .get::<&Player>(entity)
+                .ok()
+                .map(|player| player.name.clone())
+        })
+        .collect();
+
+    let info = ServerInfo {
+        name: server.config.name.clone(),
+        protocol_version: PROTOCOL_VERSION,
+        map_width,
+        map_height,
+        mode: GameMode::Deathmatch,
+        max_players: server.config.max_players,
+        players,
+    };
+
+    FromServerMessage::ServerInfo(info)
+        .construct()?
+        .send(&server.network, endpoint);
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use std::{error::Error, fmt::Display};
+
+use common::ecs::components::{InputState, LastInput};
+use common::handshake::{JoinRejection, JoinRequest};
+use common::SessionToken;
+use message_io::network::Endpoint;
+
+use crate::ecs::components::InputQueue;
+use crate::events::join::{self, JoinError};
+use crate::server::{Logger, RegisteredClient, Server};
+
+#[derive(Debug)]
+pub enum ResumeError {
+    Join(JoinError),
+}
+
+impl From<JoinError> for ResumeError {
+    fn from(value: JoinError) -> Self {
+        ResumeError::Join(value)
+    }
+}
+
+impl Display for ResumeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResumeError::Join(e) => write!(f, "ResumeError: {e}"),
+        }
+    }
+}
+
+impl Error for ResumeError {}
+
+/// Reattaches a reconnecting client to the player of its previous session.
+/// The client gets the same initial state as on join, with its old player as OwnId or as a spectator again.
+pub fn execute(
+    server: &mut Server,
+    endpoint: Endpoint,
+    token: Option<SessionToken>,
+    request: &JoinRequest,
+) -> Result<(), ResumeError> {
+    if !join::check_version(server, endpoint, request)? || server.is_registered(endpoint) {
+        return Ok(());
+    }
+
+    let logger = server.ecs.resources.get::<Logger>().unwrap().clone();
+
+    // The old endpoint might not have timed out yet if the client noticed the disconnect first
+    let previous_endpoint = token.and_then(|token| {
+        server
+            .registered_clients
+            .iter()
+            .find(|(_, client)| client.token == token)
+            .map(|(&endpoint, _)| endpoint)
+    });
+
+    let entity = match (token, previous_endpoint) {
+        (_, Some(previous_endpoint)) => server.registered_clients.remove(&previous_endpoint).map(|c| c.entity),
+        (Some(token), None) => server.suspended_clients.remove(&token).map(|s| s.entity),
+        (None, None) => None,
+    };
+
+    let (entity, token) = match (entity, token) {
+        (Some(entity), Some(token)) => (entity, token),
+        _ => {
+            logger.log(format!("Rejected resume from IP {}, no such session", endpoint.addr()));
+            join::reject(server, endpoint, JoinRejection::SessionExpired)?;
+            return Ok(());
+        }
+    };
+
+    // The client starts counting inputs from scratch
+    if let Ok((input, queue, last_input)) = server
+        .ecs
+        .world
+        .query_one_mut::<(&mut InputState, &mut InputQueue, &mut LastInput)>(entity)
+    {
+        *input = InputState::default();
+        queue.0.clear();
+
+        let mut last_input = server.ecs.observer.observe_component(entity, last_input).unreliable();
+        last_input.0 = 0;
+    }
+
+    logger.log(format!("Resumed session of participant with ip {}", endpoint.addr()));
+
+    let client = RegisteredClient::with_token(entity, Some(server.tick), token);
+    join::register(server, endpoint, client)?;
+
+    Ok(())
+}
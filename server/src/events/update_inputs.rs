@@ -1,15 +1,28 @@
 use std::{error::Error, fmt::Display};
 
-use common::ecs::components::InputState;
+use common::ecs::components::{InputState, LastInput};
+use common::net::Sequence;
+use common::Tick;
 use hecs::QueryOneError;
 use message_io::network::Endpoint;
 
+use crate::ecs::components::{InputQueue, QueuedInput};
+use crate::events::validation::{self, ValidationError};
 use crate::server::Server;
 
 #[derive(Debug)]
 pub enum InputError {
     FailedToGetPlayer,
     FailedToFindPlayerInEcs(QueryOneError),
+    Invalid(ValidationError),
+    /// The client claims to have seen a tick that hasn't happened yet
+    FutureTick(Tick),
+}
+
+impl From<ValidationError> for InputError {
+    fn from(value: ValidationError) -> Self {
+        InputError::Invalid(value)
+    }
 }
 
 impl From<QueryOneError> for InputError {
@@ -27,25 +40,58 @@ impl Display for InputError {
             InputError::FailedToFindPlayerInEcs(e) => {
                 write!(f, "client is registered but not found in ecs: {e}")
             }
+            InputError::Invalid(e) => write!(f, "rejected input: {e}"),
+            InputError::FutureTick(tick) => {
+                write!(f, "client sent an input made while looking at tick {tick} which hasn't been sent yet")
+            }
         }
     }
 }
 
 impl Error for InputError {}
 
+/// How many inputs a player can be ahead of the simulation.
+/// Anything beyond that is a client sending faster than the tick rate, the oldest inputs are dropped.
+const MAX_QUEUED_INPUTS: usize = 32;
+
+/// Queues an input to be simulated on a later tick, see the input system
 pub fn execute(
     server: &mut Server,
+    sequence: Sequence,
     updated_input_state: InputState,
+    view_tick: Tick,
     endpoint: Endpoint,
 ) -> Result<(), InputError> {
-    let entity = *server
+    let updated_input_state = validation::input_state(updated_input_state)?;
+    if view_tick > server.tick {
+        return Err(InputError::FutureTick(view_tick));
+    }
+
+    let entity = server
         .registered_clients
         .get(&endpoint)
-        .ok_or(InputError::FailedToGetPlayer)?;
+        .ok_or(InputError::FailedToGetPlayer)?
+        .entity;
 
-    let input = server.ecs.world.query_one_mut::<&mut InputState>(entity)?;
+    let (queue, last_input) = server
+        .ecs
+        .world
+        .query_one_mut::<(&mut InputQueue, &LastInput)>(entity)?;
 
-    *input = updated_input_state;
+    // UDP can reorder messages, an older input must not override a newer one
+    let newest = queue.0.back().map_or(last_input.0, |newest| newest.sequence);
+    if sequence <= newest {
+        return Ok(());
+    }
+
+    if queue.0.len() >= MAX_QUEUED_INPUTS {
+        queue.0.pop_front();
+    }
+    queue.0.push_back(QueuedInput {
+        sequence,
+        input: updated_input_state,
+        view_tick,
+    });
 
     Ok(())
 }
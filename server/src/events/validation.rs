@@ -0,0 +1,185 @@
+use std::f32::consts::TAU;
+use std::{error::Error, fmt::Display};
+
+use common::defaults::{DEFAULT_PLAYER_NAME, MAX_CHAT_LENGTH, MAX_USERNAME_LENGTH};
+use common::ecs::components::InputState;
+
+/// Ways a client can send something the server can't safely use
+#[derive(Debug)]
+pub enum ValidationError {
+    /// NaN or infinite, which would poison every direction calculated from it
+    NonFiniteLookAngle(f32),
+    UsernameTooLong { length: usize },
+    UsernameControlCharacters,
+    EmptyChatMessage,
+    ChatMessageTooLong { length: usize },
+    ChatMessageControlCharacters,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::NonFiniteLookAngle(angle) => {
+                write!(f, "look angle {angle} isn't a finite number")
+            }
+            ValidationError::UsernameTooLong { length } => write!(
+                f,
+                "username is {length} characters long, it can be at most {MAX_USERNAME_LENGTH}"
+            ),
+            ValidationError::UsernameControlCharacters => {
+                write!(f, "username can't contain control characters")
+            }
+            ValidationError::EmptyChatMessage => write!(f, "chat message is empty"),
+            ValidationError::ChatMessageTooLong { length } => write!(
+                f,
+                "chat message is {length} characters long, it can be at most {MAX_CHAT_LENGTH}"
+            ),
+            ValidationError::ChatMessageControlCharacters => {
+                write!(f, "chat message can't contain control characters")
+            }
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Rejects inputs that can't be simulated and wraps the look angle into a single turn
+pub fn input_state(input: InputState) -> Result<InputState, ValidationError> {
+    if !input.look_angle.is_finite() {
+        return Err(ValidationError::NonFiniteLookAngle(input.look_angle));
+    }
+
+    // Tiny negative angles round up to a full turn
+    let look_angle = input.look_angle.rem_euclid(TAU);
+    Ok(InputState {
+        look_angle: if look_angle < TAU { look_angle } else { 0.0 },
+        ..input
+    })
+}
+
+/// Trims the username, an empty one gets the default name
+pub fn username(username: &str) -> Result<String, ValidationError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Ok(DEFAULT_PLAYER_NAME.to_string());
+    }
+
+    let length = username.chars().count();
+    if length > MAX_USERNAME_LENGTH {
+        return Err(ValidationError::UsernameTooLong { length });
+    }
+
+    if username.chars().any(char::is_control) {
+        return Err(ValidationError::UsernameControlCharacters);
+    }
+
+    Ok(username.to_string())
+}
+
+/// Trims the chat message, refusing empty ones
+pub fn chat_message(text: &str) -> Result<String, ValidationError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(ValidationError::EmptyChatMessage);
+    }
+
+    let length = text.chars().count();
+    if length > MAX_CHAT_LENGTH {
+        return Err(ValidationError::ChatMessageTooLong { length });
+    }
+
+    if text.chars().any(char::is_control) {
+        return Err(ValidationError::ChatMessageControlCharacters);
+    }
+
+    Ok(text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn look(look_angle: f32) -> InputState {
+        InputState {
+            look_angle,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn non_finite_angles_are_rejected() {
+        for angle in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(matches!(input_state(look(angle)), Err(ValidationError::NonFiniteLookAngle(_))));
+        }
+    }
+
+    #[test]
+    fn angles_are_wrapped_into_a_single_turn() {
+        for angle in [0.0, 1.0, -1.0, TAU, -TAU, 1e30, -1e30, f32::MAX, f32::MIN, -1e-8] {
+            let wrapped = input_state(look(angle)).unwrap().look_angle;
+            assert!((0.0..TAU).contains(&wrapped), "{angle} wrapped to {wrapped}");
+        }
+
+        let wrapped = input_state(look(TAU + 1.0)).unwrap().look_angle;
+        assert!((wrapped - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn other_inputs_are_kept() {
+        let input = InputState {
+            forward: true,
+            shoot: true,
+            ..look(1.0)
+        };
+        assert_eq!(input_state(input).unwrap(), input);
+    }
+
+    #[test]
+    fn usernames_are_trimmed() {
+        assert_eq!(username("  name ").unwrap(), "name");
+        assert_eq!(username("   ").unwrap(), DEFAULT_PLAYER_NAME);
+        assert_eq!(username("").unwrap(), DEFAULT_PLAYER_NAME);
+    }
+
+    #[test]
+    fn username_length_is_counted_in_characters() {
+        let longest = "ä".repeat(MAX_USERNAME_LENGTH);
+        assert_eq!(username(&longest).unwrap(), longest);
+
+        let too_long = "a".repeat(MAX_USERNAME_LENGTH + 1);
+        assert!(matches!(
+            username(&too_long),
+            Err(ValidationError::UsernameTooLong { length }) if length == MAX_USERNAME_LENGTH + 1
+        ));
+    }
+
+    #[test]
+    fn usernames_with_control_characters_are_rejected() {
+        assert!(matches!(username("a\nb"), Err(ValidationError::UsernameControlCharacters)));
+        assert!(matches!(username("a\u{1b}[31mb"), Err(ValidationError::UsernameControlCharacters)));
+    }
+
+    #[test]
+    fn chat_messages_are_trimmed() {
+        assert_eq!(chat_message(" hello ").unwrap(), "hello");
+        assert!(matches!(chat_message(""), Err(ValidationError::EmptyChatMessage)));
+        assert!(matches!(chat_message(" \t "), Err(ValidationError::EmptyChatMessage)));
+    }
+
+    #[test]
+    fn chat_message_length_is_bounded() {
+        let longest = "ä".repeat(MAX_CHAT_LENGTH);
+        assert_eq!(chat_message(&longest).unwrap(), longest);
+
+        let too_long = "a".repeat(MAX_CHAT_LENGTH + 1);
+        assert!(matches!(
+            chat_message(&too_long),
+            Err(ValidationError::ChatMessageTooLong { length }) if length == MAX_CHAT_LENGTH + 1
+        ));
+    }
+
+    #[test]
+    fn chat_messages_with_control_characters_are_rejected() {
+        assert!(matches!(chat_message("a\rb"), Err(ValidationError::ChatMessageControlCharacters)));
+    }
+}
@@ -3,18 +3,21 @@ use std::{
     net::{IpAddr, SocketAddr},
 };
 
-use crate::server::Server;
+use crate::server::{Server, ServerConfig};
 
 pub mod ecs;
 pub mod events;
 pub mod server;
 mod constructed_message;
+mod rate_limit;
+mod relevance;
+mod snapshot;
 
 
-pub fn run_server(ip: IpAddr, port: u16) -> io::Result<()> {
+pub fn run_server(ip: IpAddr, port: u16, config: ServerConfig) -> io::Result<()> {
     let addr = SocketAddr::new(ip, port);
     println!("Starting server on {addr}");
-    let (mut server, _) = Server::new(addr, false)?;
+    let (mut server, _) = Server::new(addr, config, false)?;
     server.run();
 
     Ok(())
@@ -1,11 +1,17 @@
 use std::io;
 use std::net::IpAddr;
 
+use std::time::Duration;
+
 use common::defaults::IP;
 use common::defaults::PORT;
+use common::defaults::{CLIENT_TIMEOUT, DEFAULT_SERVER_NAME, MAX_PLAYERS, MAX_SPECTATORS};
 
 use clap::Parser;
 use server::run_server;
+use server::server::ServerConfig;
+use common::net::simulator::SimArgs;
+use common::net::transport::GameTransport;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -17,13 +23,53 @@ struct Args {
     /// IP to host server on
     #[arg(short, long, default_value_t = IP)]
     ip: IpAddr,
+
+    /// How many players can be connected at once
+    #[arg(long, default_value_t = MAX_PLAYERS)]
+    max_players: usize,
+
+    /// How many spectators can be connected at once, they don't count towards the players
+    #[arg(long, default_value_t = MAX_SPECTATORS)]
+    max_spectators: usize,
+
+    /// Seconds without a ping or input before a client is removed
+    #[arg(long, default_value_t = CLIENT_TIMEOUT.as_secs())]
+    client_timeout: u64,
+
+    /// Transport to accept clients on, can be given several times to listen on each of them.
+    /// One of udp, tcp or ws, WebSocket runs on the port after the game port
+    #[arg(short, long, default_value = "udp")]
+    transport: Vec<GameTransport>,
+
+    /// Name shown in server lists
+    #[arg(short, long, default_value = DEFAULT_SERVER_NAME)]
+    name: String,
+
+    /// Master server to list this server on, like fps.catnip.ee:1339. Stays unlisted if not given
+    #[arg(long)]
+    master: Option<String>,
+
+    // Bad connection to simulate for everything the server sends
+    #[command(flatten)]
+    sim: SimArgs,
 }
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
     let args = Args::parse();
 
-    run_server(args.ip, args.port)?;
+    let config = ServerConfig {
+        name: args.name,
+        master: args.master,
+        transports: args.transport,
+        max_players: args.max_players,
+        max_spectators: args.max_spectators,
+        client_timeout: Duration::from_secs(args.client_timeout),
+        simulate: args.sim.conditions(),
+        ..ServerConfig::default()
+    };
+
+    run_server(args.ip, args.port, config)?;
 
     Ok(())
 }
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use common::FromClientMessage;
+
+/// Datagrams an endpoint can send per second before anything is even decoded
+const DATAGRAMS_PER_SECOND: f32 = 1000.0;
+
+/// Endpoints that get this many messages dropped within a second are temporarily banned
+const FLOOD_THRESHOLD: u32 = 250;
+
+/// How long a flooding address is ignored for
+pub const TEMP_BAN_DURATION: Duration = Duration::from_secs(60);
+
+/// Limiters of endpoints that have been quiet for this long are forgotten
+pub const LIMITER_EXPIRY: Duration = Duration::from_secs(30);
+
+/// Most endpoints tracked at once, so spoofed addresses can't grow the limiters without bound.
+/// Registered clients always get one, everyone else is dropped until some expire.
+pub const MAX_LIMITERS: usize = 4096;
+
+/// Refills continuously up to its capacity, every message takes a token
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f32,
+    capacity: f32,
+    per_second: f32,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f32, per_second: f32) -> Self {
+        TokenBucket {
+            tokens: capacity,
+            capacity,
+            per_second,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn try_take(&mut self) -> bool {
+        let now = Instant::now();
+        let refill = (now - self.last_refill).as_secs_f32() * self.per_second;
+        self.tokens = (self.tokens + refill).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Messages that share a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    Ping,
+    Leave,
+    /// Joining and resuming, the client retries these until it hears back
+    Join,
+    UpdateInputs,
+    Ack,
+    AckSnapshot,
+    Query,
+    Chat,
+    /// A spectator asking for a player slot
+    Play,
+    /// Datagrams that didn't decode, only used to limit how often they're logged
+    Invalid,
+}
+
+impl MessageKind {
+    pub fn of(message: &FromClientMessage) -> Self {
+        match message {
+            FromClientMessage::Ping => MessageKind::Ping,
+            FromClientMessage::Leave => MessageKind::Leave,
+            FromClientMessage::Join(_) | FromClientMessage::Resume(_) => MessageKind::Join,
+            FromClientMessage::UpdateInputs(..) => MessageKind::UpdateInputs,
+            FromClientMessage::Ack(_) => MessageKind::Ack,
+            FromClientMessage::AckSnapshot(_) => MessageKind::AckSnapshot,
+            FromClientMessage::Query => MessageKind::Query,
+            FromClientMessage::Chat(_) => MessageKind::Chat,
+            FromClientMessage::Play => MessageKind::Play,
+        }
+    }
+
+    /// Burst size and sustained messages per second.
+    /// Inputs and snapshot acks are sent every frame, reliable acks for every reliable packet including resends.
+    fn limit(self) -> (f32, f32) {
+        match self {
+            MessageKind::Ping => (10.0, 5.0),
+            MessageKind::Leave => (5.0, 2.0),
+            MessageKind::Join => (5.0, 2.0),
+            MessageKind::UpdateInputs => (300.0, 300.0),
+            MessageKind::Ack => (600.0, 600.0),
+            MessageKind::AckSnapshot => (300.0, 300.0),
+            MessageKind::Query => (10.0, 5.0),
+            // A few quick lines are fine, a wall of them isn't
+            MessageKind::Chat => (5.0, 1.0),
+            MessageKind::Play => (5.0, 2.0),
+            MessageKind::Invalid => (5.0, 1.0),
+        }
+    }
+}
+
+/// Limits of a single endpoint
+#[derive(Debug)]
+pub struct RateLimiter {
+    datagrams: TokenBucket,
+    kinds: HashMap<MessageKind, TokenBucket>,
+
+    window_start: Instant,
+    dropped_in_window: u32,
+
+    pub last_seen: Instant,
+    /// Every message dropped from this endpoint
+    pub dropped: u64,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter {
+            datagrams: TokenBucket::new(DATAGRAMS_PER_SECOND, DATAGRAMS_PER_SECOND),
+            kinds: HashMap::new(),
+            window_start: Instant::now(),
+            dropped_in_window: 0,
+            last_seen: Instant::now(),
+            dropped: 0,
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Checked for every datagram before decoding it
+    pub fn allow_datagram(&mut self) -> bool {
+        self.last_seen = Instant::now();
+        self.datagrams.try_take()
+    }
+
+    pub fn allow(&mut self, kind: MessageKind) -> bool {
+        let (capacity, per_second) = kind.limit();
+        self.kinds
+            .entry(kind)
+            .or_insert_with(|| TokenBucket::new(capacity, per_second))
+            .try_take()
+    }
+
+    /// Counts a dropped message, returns true once the endpoint is dropping enough to be flooding
+    pub fn record_drop(&mut self) -> bool {
+        if self.window_start.elapsed() >= Duration::from_secs(1) {
+            self.window_start = Instant::now();
+            self.dropped_in_window = 0;
+        }
+
+        self.dropped += 1;
+        self.dropped_in_window += 1;
+        self.dropped_in_window >= FLOOD_THRESHOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_bursts_up_to_its_capacity() {
+        let mut bucket = TokenBucket::new(3.0, 0.0);
+        assert!(bucket.try_take());
+        assert!(bucket.try_take());
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+    }
+
+    #[test]
+    fn bucket_refills_over_time_but_not_past_its_capacity() {
+        let mut bucket = TokenBucket::new(2.0, 1.0);
+        bucket.tokens = 0.0;
+        assert!(!bucket.try_take());
+
+        bucket.last_refill -= Duration::from_secs(1);
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+
+        bucket.last_refill -= Duration::from_secs(10);
+        assert!(bucket.try_take());
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+    }
+
+    #[test]
+    fn kinds_are_limited_separately() {
+        let mut limiter = RateLimiter::default();
+        let (burst, _) = MessageKind::Chat.limit();
+        for _ in 0..burst as usize {
+            assert!(limiter.allow(MessageKind::Chat));
+        }
+        assert!(!limiter.allow(MessageKind::Chat));
+        assert!(limiter.allow(MessageKind::Ping));
+    }
+
+    #[test]
+    fn flooding_is_detected_within_a_second() {
+        let mut limiter = RateLimiter::default();
+        for _ in 1..FLOOD_THRESHOLD {
+            assert!(!limiter.record_drop());
+        }
+        assert!(limiter.record_drop());
+        assert_eq!(limiter.dropped, FLOOD_THRESHOLD as u64);
+    }
+
+    #[test]
+    fn drops_spread_over_time_are_not_flooding() {
+        let mut limiter = RateLimiter::default();
+        for _ in 1..FLOOD_THRESHOLD {
+            assert!(!limiter.record_drop());
+        }
+
+        limiter.window_start -= Duration::from_secs(1);
+        assert!(!limiter.record_drop());
+        // The total keeps counting across windows
+        assert_eq!(limiter.dropped, FLOOD_THRESHOLD as u64);
+    }
+}
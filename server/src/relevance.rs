@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+
+use common::defaults::TICKS_PER_SECOND;
+use common::ecs::components::{EcsProtocol, Position};
+use common::map::Map;
+use common::Tick;
+use glam::Vec2;
+use hecs::{Entity, World};
+
+/// Entities further away than this are never replicated
+pub const RELEVANCE_DISTANCE: f32 = 16.0;
+
+/// Entities stay relevant for a while after going out of sight,
+/// so peeking around a corner doesn't spawn and despawn them every other tick
+const RELEVANCE_LINGER: Tick = TICKS_PER_SECOND / 2;
+
+/// Where every entity that's subject to relevance filtering is this tick.
+/// Entities without a position are relevant to everyone.
+#[derive(Debug, Default)]
+pub struct Spatial(HashMap<NonZeroU64, (Entity, Vec2)>);
+
+impl Spatial {
+    pub fn new(world: &mut World) -> Self {
+        Spatial(
+            world
+                .query_mut::<&Position>()
+                .into_iter()
+                .map(|(entity, pos)| (entity.to_bits(), (entity, pos.0)))
+                .collect(),
+        )
+    }
+
+    fn position(&self, id: NonZeroU64) -> Option<Vec2> {
+        self.0.get(&id).map(|&(_, pos)| pos)
+    }
+
+    fn contains(&self, id: NonZeroU64) -> bool {
+        self.0.contains_key(&id)
+    }
+}
+
+/// Whether something at `target` can be perceived from `viewer`
+pub fn is_visible(map: &Map, viewer: Vec2, target: Vec2) -> bool {
+    viewer.distance(target) <= RELEVANCE_DISTANCE && map.line_of_sight(viewer, target)
+}
+
+/// Entities that have been spawned on a single client
+#[derive(Debug, Default)]
+pub struct Relevance {
+    /// Last tick each entity was visible to the client
+    visible: HashMap<NonZeroU64, Tick>,
+}
+
+/// Entities that have to be spawned or despawned on a client
+#[derive(Debug, Default)]
+pub struct RelevanceChanges {
+    pub entered: Vec<Entity>,
+    pub left: Vec<NonZeroU64>,
+}
+
+impl Relevance {
+    /// Recomputes what the client with the given player can perceive.
+    /// Spectators have no player and can go anywhere, so everything is relevant to them.
+    pub fn update(&mut self, tick: Tick, viewer: Option<Entity>, spatial: &Spatial, map: &Map) -> RelevanceChanges {
+        let mut changes = RelevanceChanges::default();
+
+        let viewer = match viewer {
+            Some(viewer) => match spatial.position(viewer.to_bits()) {
+                Some(pos) => Some((viewer, pos)),
+                None => return changes,
+            },
+            None => None,
+        };
+
+        for (&id, &(entity, pos)) in &spatial.0 {
+            if let Some((viewer, viewer_pos)) = viewer {
+                if entity != viewer && !is_visible(map, viewer_pos, pos) {
+                    continue;
+                }
+            }
+
+            if self.visible.insert(id, tick).is_none() {
+                changes.entered.push(entity);
+            }
+        }
+
+        self.visible.retain(|&id, &mut last_visible| {
+            // Despawned entities are already despawned on the client through the reliable channel
+            if !spatial.contains(id) {
+                return false;
+            }
+
+            let relevant = last_visible + RELEVANCE_LINGER >= tick;
+            if !relevant {
+                changes.left.push(id);
+            }
+            relevant
+        });
+
+        changes
+    }
+
+    /// Drops changes to entities the client shouldn't know about
+    pub fn filter(&self, changes: &[EcsProtocol], spatial: &Spatial) -> Vec<EcsProtocol> {
+        changes
+            .iter()
+            .filter(|change| {
+                let id = match change {
+                    EcsProtocol::Insert((id, _))
+                    | EcsProtocol::Remove((id, _))
+                    | EcsProtocol::Despawn(id) => *id,
+                };
+
+                !spatial.contains(id) || self.visible.contains_key(&id)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::ecs::components::{InsertComponent, Position, RemoveComponent};
+    use common::map::{MapCell, Wall};
+
+    use super::*;
+
+    fn spawn(world: &mut World, x: f32, y: f32) -> Entity {
+        world.spawn((Position(Vec2::new(x, y)),))
+    }
+
+    #[test]
+    fn visible_entities_enter_once() {
+        let mut world = World::new();
+        let viewer = spawn(&mut world, 1.5, 1.5);
+        let near = spawn(&mut world, 5.5, 1.5);
+        let far = spawn(&mut world, 1.5 + RELEVANCE_DISTANCE + 1.0, 1.5);
+        let map = Map::new(40, 40);
+        let spatial = Spatial::new(&mut world);
+
+        let mut relevance = Relevance::default();
+        let changes = relevance.update(1, Some(viewer), &spatial, &map);
+        assert_eq!(changes.entered.len(), 2);
+        assert!(changes.entered.contains(&viewer));
+        assert!(changes.entered.contains(&near));
+        assert!(!changes.entered.contains(&far));
+
+        let changes = relevance.update(2, Some(viewer), &spatial, &map);
+        assert!(changes.entered.is_empty());
+        assert!(changes.left.is_empty());
+    }
+
+    #[test]
+    fn walls_block_sight() {
+        let mut world = World::new();
+        let viewer = spawn(&mut world, 1.5, 1.5);
+        let hidden = spawn(&mut world, 5.5, 1.5);
+        let mut map = Map::new(40, 40);
+        *map.cell_mut(3, 1) = MapCell::Wall(Wall::default());
+        let spatial = Spatial::new(&mut world);
+
+        let changes = Relevance::default().update(1, Some(viewer), &spatial, &map);
+        assert_eq!(changes.entered, vec![viewer]);
+        assert!(!changes.entered.contains(&hidden));
+    }
+
+    #[test]
+    fn entities_linger_before_leaving() {
+        let mut world = World::new();
+        let viewer = spawn(&mut world, 1.5, 1.5);
+        let other = spawn(&mut world, 5.5, 1.5);
+        let map = Map::new(40, 40);
+
+        let mut relevance = Relevance::default();
+        relevance.update(1, Some(viewer), &Spatial::new(&mut world), &map);
+
+        world.insert_one(other, Position(Vec2::new(35.5, 1.5))).unwrap();
+        let spatial = Spatial::new(&mut world);
+
+        let changes = relevance.update(1 + RELEVANCE_LINGER, Some(viewer), &spatial, &map);
+        assert!(changes.left.is_empty());
+
+        let changes = relevance.update(2 + RELEVANCE_LINGER, Some(viewer), &spatial, &map);
+        assert_eq!(changes.left, vec![other.to_bits()]);
+
+        // Coming back into sight spawns it again
+        world.insert_one(other, Position(Vec2::new(5.5, 1.5))).unwrap();
+        let changes = relevance.update(3 + RELEVANCE_LINGER, Some(viewer), &Spatial::new(&mut world), &map);
+        assert_eq!(changes.entered, vec![other]);
+    }
+
+    #[test]
+    fn despawned_entities_are_forgotten_without_leaving() {
+        let mut world = World::new();
+        let viewer = spawn(&mut world, 1.5, 1.5);
+        let other = spawn(&mut world, 5.5, 1.5);
+        let map = Map::new(40, 40);
+
+        let mut relevance = Relevance::default();
+        relevance.update(1, Some(viewer), &Spatial::new(&mut world), &map);
+
+        world.despawn(other).unwrap();
+        let changes = relevance.update(2 + RELEVANCE_LINGER, Some(viewer), &Spatial::new(&mut world), &map);
+        assert!(changes.left.is_empty());
+    }
+
+    #[test]
+    fn spectators_see_everything() {
+        let mut world = World::new();
+        let near = spawn(&mut world, 1.5, 1.5);
+        let far = spawn(&mut world, 1.5 + RELEVANCE_DISTANCE + 1.0, 1.5);
+        let mut map = Map::new(40, 40);
+        *map.cell_mut(3, 1) = MapCell::Wall(Wall::default());
+        let spatial = Spatial::new(&mut world);
+
+        let changes = Relevance::default().update(1, None, &spatial, &map);
+        assert_eq!(changes.entered.len(), 2);
+        assert!(changes.entered.contains(&near));
+        assert!(changes.entered.contains(&far));
+    }
+
+    #[test]
+    fn viewers_without_a_position_see_nothing() {
+        let mut world = World::new();
+        let viewer = world.spawn(());
+        spawn(&mut world, 1.5, 1.5);
+        let spatial = Spatial::new(&mut world);
+
+        let changes = Relevance::default().update(1, Some(viewer), &spatial, &Map::new(40, 40));
+        assert!(changes.entered.is_empty());
+    }
+
+    #[test]
+    fn filter_drops_changes_to_unseen_entities() {
+        let mut world = World::new();
+        let viewer = spawn(&mut world, 1.5, 1.5);
+        let far = spawn(&mut world, 1.5 + RELEVANCE_DISTANCE + 1.0, 1.5);
+        let unpositioned = world.spawn(());
+        let map = Map::new(40, 40);
+        let spatial = Spatial::new(&mut world);
+
+        let mut relevance = Relevance::default();
+        relevance.update(1, Some(viewer), &spatial, &map);
+
+        let position = |entity: Entity| {
+            EcsProtocol::Insert((entity.to_bits(), InsertComponent::Position(Position(Vec2::ZERO))))
+        };
+        let changes = [
+            position(viewer),
+            position(far),
+            EcsProtocol::Remove((far.to_bits(), RemoveComponent::Position)),
+            EcsProtocol::Despawn(far.to_bits()),
+            EcsProtocol::Despawn(unpositioned.to_bits()),
+        ];
+
+        let filtered = relevance.filter(&changes, &spatial);
+        assert_eq!(filtered, vec![position(viewer), EcsProtocol::Despawn(unpositioned.to_bits())]);
+    }
+}
@@ -1,40 +1,206 @@
 use chrono::Utc;
-use common::defaults::{MAP_HEIGHT, MAP_WIDTH, TICKS_PER_SECOND};
-use common::ecs::components::EcsProtocol;
+use common::defaults::{
+    CLIENT_TIMEOUT, DEFAULT_SERVER_NAME, DISCOVERY_PORT, MAP_HEIGHT, MAP_WIDTH, MAX_PLAYERS,
+    MAX_SPECTATORS, RESUME_GRACE_PERIOD, TICKS_PER_SECOND,
+};
+use common::ecs::components::{Deaths, EcsProtocol, InputState, InsertComponent, Kills, Player};
+use common::snapshot::Snapshot;
 use common::map::Map;
 use message_io::node::NodeEvent;
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
 use hecs::Entity;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::io;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use crate::constructed_message::ConstructMessage;
+use crate::constructed_message::{send_tracked, ConstructMessage};
+use crate::rate_limit::{MessageKind, RateLimiter, LIMITER_EXPIRY, MAX_LIMITERS, TEMP_BAN_DURATION};
+use crate::relevance::{Relevance, Spatial};
 use crate::ecs::spawn::weapon_crate::spawn_weapon_crates_init;
-use common::{FromClientMessage, FromServerMessage, Signal};
+use crate::snapshot::{SnapshotStore, MAX_BASELINE_AGE};
+use common::net::codec;
+use common::net::channel::{ReliableSender, RESEND_TIMEOUT};
+use common::handshake::{JoinRejection, PROTOCOL_VERSION};
+use common::master::{FromMasterMessage, Heartbeat, ToMasterMessage, HEARTBEAT_INTERVAL};
+use common::net::simulator::{NetConditions, Network};
+use common::net::transport::GameTransport;
+use common::net::stats::{NetStats, NetStatsSummary};
+use common::{
+    ClientMessage, DisconnectReason, FromClientMessage, FromServerMessage, Scoreboard,
+    ScoreboardEntry, SessionToken, Signal, Tick,
+};
 use message_io::{
-    network::{Endpoint, NetEvent, Transport},
+    network::{Endpoint, NetEvent, ResourceId, Transport},
     node::{self, NodeHandler, NodeListener},
 };
 
+use crate::ecs::components::InputQueue;
+use crate::ecs::history::{PositionHistory, ServerTick};
 use crate::ecs::ServerEcs;
 use crate::events;
 
 pub struct Server {
     last_tick: Instant,
+    pub tick: Tick,
+
+    /// Address the game is hosted on
+    pub addr: SocketAddr,
+    /// Socket answering LAN discovery probes, None if the server isn't reachable from the LAN
+    discovery: Option<ResourceId>,
+    /// Master server this server is listed on, connected when the server starts running
+    master: Option<Endpoint>,
+    /// Nonce the master server challenged us with, heartbeats without it don't get us listed
+    master_nonce: Option<u64>,
+    last_heartbeat: Option<Instant>,
 
     pub handler: NodeHandler<Signal>,
+    /// Everything sent to clients goes through here
+    pub network: Network<Signal>,
     listener: Option<NodeListener<Signal>>,
 
     pub registered_clients: RegisteredClients,
+    /// Players of timed out clients, kept around in case the client comes back
+    pub suspended_clients: HashMap<SessionToken, SuspendedClient>,
     pub ecs: ServerEcs,
+    pub snapshots: SnapshotStore,
+
+    /// Addresses that aren't allowed to join anymore
+    pub banned: HashSet<IpAddr>,
+    /// Addresses that flooded the server, everything from them is ignored until the time is up
+    pub temp_bans: HashMap<IpAddr, Instant>,
+    limiters: HashMap<Endpoint, RateLimiter>,
+    dropped: DropCounters,
+    pub config: ServerConfig,
+
+    /// Connection quality of every client, refreshed once a second
+    pub net_stats: SharedNetStats,
+}
+
+/// Lets the admin console read client stats while the server runs on another task
+pub type SharedNetStats = Arc<Mutex<NetReport>>;
+
+#[derive(Debug, Clone, Default)]
+pub struct NetReport {
+    pub clients: Vec<ClientNetStats>,
+    pub dropped: DropCounters,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientNetStats {
+    pub name: String,
+    pub addr: SocketAddr,
+    pub stats: NetStatsSummary,
+    /// Messages from this client that were over its rate limits
+    pub dropped: u64,
+}
+
+/// Messages the server threw away without processing them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DropCounters {
+    /// Over an endpoint's rate limits
+    pub rate_limited: u64,
+    /// Didn't decode or carried the wrong session token
+    pub invalid: u64,
+    /// From a temporarily banned address
+    pub banned: u64,
+}
+
+/// Tunables for running a server
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Shown to players looking for a server
+    pub name: String,
+    pub max_players: usize,
+
+    /// How many clients can watch without playing, on top of the players
+    pub max_spectators: usize,
+
+    /// Clients that haven't sent a ping or input for this long get removed
+    pub client_timeout: Duration,
+
+    /// How long a timed out client can still resume its session
+    pub resume_grace_period: Duration,
+
+    /// Bad connection to simulate on everything the server sends, for testing
+    pub simulate: Option<NetConditions>,
+
+    /// Address of the master server to get listed on, the server stays unlisted if None
+    pub master: Option<String>,
+
+    /// Transports clients can connect with, the server listens on all of them at once
+    pub transports: Vec<GameTransport>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            name: DEFAULT_SERVER_NAME.to_string(),
+            max_players: MAX_PLAYERS,
+            max_spectators: MAX_SPECTATORS,
+            client_timeout: CLIENT_TIMEOUT,
+            resume_grace_period: RESUME_GRACE_PERIOD,
+            simulate: None,
+            master: None,
+            transports: vec![GameTransport::Udp],
+        }
+    }
+}
+
+/// Maps endpoints to their client state
+pub type RegisteredClients = HashMap<Endpoint, RegisteredClient>;
+
+pub struct RegisteredClient {
+    /// The player entity of this client, or its spectator entity when it's only watching
+    pub entity: Entity,
+
+    /// Latest snapshot tick the client has acknowledged.
+    /// Snapshots sent to this client are deltas against it.
+    pub acked_tick: Option<Tick>,
+
+    /// Reliable channel for spawns, despawns and other changes that can't be lost
+    pub reliable: ReliableSender,
+
+    /// Entities this client can perceive, nothing else gets replicated to it
+    pub relevance: Relevance,
+
+    /// When the client last showed signs of life with a ping or input
+    pub last_activity: Instant,
+
+    /// Messages from this client's endpoint are only trusted if they carry this token
+    pub token: SessionToken,
+
+    /// Round trip time, loss and bandwidth of this client's connection
+    pub stats: NetStats,
 }
 
-/// Maps endpoints to their player entity
-pub type RegisteredClients = HashMap<Endpoint, Entity>;
+impl RegisteredClient {
+    /// A client with a freshly generated session token
+    pub fn new(entity: Entity, acked_tick: Option<Tick>) -> Self {
+        Self::with_token(entity, acked_tick, rand::random())
+    }
+
+    pub fn with_token(entity: Entity, acked_tick: Option<Tick>, token: SessionToken) -> Self {
+        Self {
+            entity,
+            token,
+            acked_tick,
+            reliable: ReliableSender::default(),
+            relevance: Relevance::default(),
+            last_activity: Instant::now(),
+            stats: NetStats::default(),
+        }
+    }
+}
+
+/// A player whose client went silent
+pub struct SuspendedClient {
+    pub entity: Entity,
+    pub since: Instant,
+}
 
 #[derive(Clone)]
 pub struct Logger {
@@ -72,25 +238,68 @@ impl Logger {
 impl Server {
     pub fn new(
         addr: SocketAddr,
+        config: ServerConfig,
         enable_logging_channels: bool,
     ) -> io::Result<(Self, UnboundedReceiver<String>)> {
         let (handler, listener) = node::split::<Signal>();
+        let (logger, logger_receiver) = Logger::new(enable_logging_channels);
 
-        handler.network().listen(Transport::Udp, addr)?;
+        for &transport in &config.transports {
+            let port = transport
+                .port(addr.port())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            let transport_addr = SocketAddr::new(addr.ip(), port);
+            handler.network().listen(transport.message_io(), transport_addr)?;
+            logger.log(format!("Listening for {transport} on {transport_addr}"));
+        }
 
         let mut ecs = ServerEcs::default();
         ecs.resources.insert(Map::gen(MAP_WIDTH, MAP_HEIGHT));
+        ecs.resources.insert(ServerTick(0));
+        ecs.resources.insert(PositionHistory::default());
         spawn_weapon_crates_init(&mut ecs);
-        let (logger, logger_receiver) = Logger::new(enable_logging_channels);
+
+        // Nobody else on the LAN could join a server that only listens on loopback
+        let discovery = if addr.ip().is_loopback() {
+            None
+        } else {
+            match handler
+                .network()
+                .listen(Transport::Udp, (Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))
+            {
+                Ok((id, _)) => Some(id),
+                Err(err) => {
+                    logger.log(format!(
+                        "Warning: LAN discovery is disabled, failed to listen on port {DISCOVERY_PORT}: {err}"
+                    ));
+                    None
+                }
+            }
+        };
         ecs.resources.insert(logger);
 
         Ok((
             Server {
                 last_tick: Instant::now(),
+                tick: 0,
+                addr,
+                discovery,
+                master: None,
+                master_nonce: None,
+                last_heartbeat: None,
+                network: Network::new(handler.clone()),
                 handler,
                 listener: Some(listener),
                 registered_clients: RegisteredClients::new(),
+                suspended_clients: HashMap::new(),
                 ecs,
+                snapshots: SnapshotStore::default(),
+                banned: HashSet::new(),
+                temp_bans: HashMap::new(),
+                limiters: HashMap::new(),
+                dropped: DropCounters::default(),
+                config,
+                net_stats: SharedNetStats::default(),
             },
             logger_receiver,
         ))
@@ -99,28 +308,424 @@ impl Server {
     pub fn handle_ticks(&mut self) {
         let dt = self.last_tick.elapsed().as_secs_f32();
         self.last_tick = Instant::now();
+        self.tick += 1;
+
+        // Lag compensation rewinds from here to the tick each player's input was made at
+        self.ecs.resources.insert(ServerTick(self.tick));
+
         self.ecs.tick(dt);
 
-        let protocols = self
+        let reliable = self
             .ecs
             .observer
             .drain_reliable()
             .collect::<Vec<EcsProtocol>>();
+        let unreliable = self
+            .ecs
+            .observer
+            .drain_unreliable()
+            .collect::<Vec<EcsProtocol>>();
 
-        if !protocols.is_empty() {
-            FromServerMessage::EcsChanges(protocols)
-                .construct()
-                .unwrap()
-                .send_all(&self.handler, &self.registered_clients);
+        // Movement is latest-wins, so it only goes into the snapshots
+        self.snapshots.record(self.tick, unreliable);
+
+        // Despawned entities are delivered reliably, the snapshots just need to stop including them
+        for change in &reliable {
+            if let EcsProtocol::Despawn(entity) = change {
+                self.snapshots.forget(*entity);
+            }
         }
+
+        let spatial = Spatial::new(&mut self.ecs.world);
+        self.send_reliable_changes(&reliable, &spatial);
+
+        self.send_snapshots(&spatial);
+        if self.tick.is_multiple_of(TICKS_PER_SECOND) {
+            self.send_scoreboard();
+            self.publish_net_stats();
+        }
+        self.drop_silent_clients();
+        self.resend_reliable();
+        if self.last_heartbeat.map_or(true, |last| last.elapsed() >= HEARTBEAT_INTERVAL) {
+            self.send_heartbeat();
+        }
+
         self.handler
             .signals()
             .send_with_timer(Signal::Tick, Duration::from_millis(1000 / TICKS_PER_SECOND));
     }
 
+    /// Sends every client this tick's reliable changes to entities it can perceive,
+    /// along with spawns and despawns of entities entering or leaving its relevance
+    fn send_reliable_changes(&mut self, reliable: &[EcsProtocol], spatial: &Spatial) {
+        let map = self.ecs.resources.get::<Map>().unwrap();
+        let logger = self.ecs.resources.get::<Logger>().unwrap().clone();
+        // Can't leave these clients with a hole in their state, they have to join again
+        let mut failed = Vec::new();
+
+        for (&endpoint, client) in self.registered_clients.iter_mut() {
+            let viewer = Some(client.entity).filter(|&entity| !self.ecs.is_spectator(entity));
+            let relevance = client.relevance.update(self.tick, viewer, spatial, &map);
+
+            let mut changes: Vec<EcsProtocol> = relevance
+                .entered
+                .into_iter()
+                .flat_map(|entity| InsertComponent::query_entity(&self.ecs.world, entity))
+                .collect();
+            changes.extend(relevance.left.into_iter().map(EcsProtocol::Despawn));
+            changes.extend(client.relevance.filter(reliable, spatial));
+
+            if !changes.is_empty() {
+                match FromServerMessage::EcsChanges(changes).construct() {
+                    Ok(message) => message.send_reliable(&self.network, endpoint, client),
+                    Err(err) => {
+                        logger.log(format!("Warning: couldn't send changes to IP {}: {err}", endpoint.addr()));
+                        failed.push(endpoint);
+                    }
+                }
+            }
+        }
+
+        drop(map);
+        self.disconnect_all(failed, &logger);
+    }
+
+    /// Removes clients the server couldn't keep in sync
+    fn disconnect_all(&mut self, endpoints: Vec<Endpoint>, logger: &Logger) {
+        for endpoint in endpoints {
+            let reason = DisconnectReason::Kicked("The server couldn't send you the game state".to_string());
+            if let Err(err) = events::disconnect::execute(self, endpoint, reason) {
+                logger.log(format!("Warning: {err}"))
+            }
+        }
+    }
+
+    /// Sends every client the changes since the last snapshot it acknowledged
+    fn send_snapshots(&mut self, spatial: &Spatial) {
+        // Clients with the same baseline get the same delta, so it's only built once per baseline
+        let mut deltas: HashMap<Option<Tick>, Snapshot> = HashMap::new();
+        let logger = self.ecs.resources.get::<Logger>().unwrap().clone();
+        // The delta only grows until they ack one, so these clients would never get a snapshot again
+        let mut failed = Vec::new();
+
+        for (&endpoint, client) in self.registered_clients.iter_mut() {
+            let delta = deltas
+                .entry(client.acked_tick)
+                .or_insert_with(|| self.snapshots.delta(self.tick, client.acked_tick));
+
+            let changes = client.relevance.filter(&delta.changes, spatial);
+            if changes.is_empty() {
+                // Nothing changed since the baseline, so the client already has the current state
+                if client.acked_tick.is_some() {
+                    client.acked_tick = Some(self.tick);
+                }
+                continue;
+            }
+
+            // The ack for this snapshot doubles as a round trip measurement
+            client.stats.sent_probe(self.tick);
+            let snapshot = FromServerMessage::Snapshot(Snapshot {
+                tick: delta.tick,
+                baseline: delta.baseline,
+                changes,
+            });
+            match snapshot.construct() {
+                Ok(message) => message.send_to(&self.network, endpoint, client),
+                Err(err) => {
+                    logger.log(format!("Warning: couldn't send a snapshot to IP {}: {err}", endpoint.addr()));
+                    failed.push(endpoint);
+                }
+            }
+        }
+        self.disconnect_all(failed, &logger);
+
+        let oldest_baseline = self
+            .registered_clients
+            .values()
+            .filter_map(|client| client.acked_tick)
+            .min()
+            .unwrap_or(self.tick);
+
+        self.snapshots
+            .prune(oldest_baseline.max(self.tick.saturating_sub(MAX_BASELINE_AGE)));
+    }
+
+    /// Out of sight players aren't replicated, so the scoreboard is sent separately
+    fn send_scoreboard(&mut self) {
+        let pings: HashMap<Entity, Option<u32>> = self
+            .registered_clients
+            .values_mut()
+            .map(|client| (client.entity, client.stats.summary().ping_ms()))
+            .collect();
+
+        let entries = self
+            .ecs
+            .world
+            .query_mut::<(&Player, &Kills, &Deaths)>()
+            .into_iter()
+            .map(|(entity, (player, kills, deaths))| ScoreboardEntry {
+                id: player.id,
+                name: player.name.clone(),
+                kills: kills.0,
+                deaths: deaths.0,
+                ping: pings.get(&entity).copied().flatten(),
+            })
+            .collect();
+
+        // Another one goes out in a second, so a scoreboard that doesn't fit is only worth a warning
+        match FromServerMessage::Scoreboard(Scoreboard(entries)).construct() {
+            Ok(message) => message.send_all(&self.network, &mut self.registered_clients),
+            Err(err) => self.ecs.resources.get::<Logger>().unwrap().log(format!("Warning: couldn't send the scoreboard: {err}")),
+        }
+    }
+
+    fn publish_net_stats(&mut self) {
+        self.limiters
+            .retain(|_, limiter| limiter.last_seen.elapsed() < LIMITER_EXPIRY);
+        self.temp_bans.retain(|_, until| *until > Instant::now());
+
+        let clients = self
+            .registered_clients
+            .iter_mut()
+            .map(|(endpoint, client)| ClientNetStats {
+                name: self.ecs.participant_name(client.entity).unwrap_or_default(),
+                addr: endpoint.addr(),
+                stats: client.stats.summary(),
+                dropped: self.limiters.get(endpoint).map_or(0, |limiter| limiter.dropped),
+            })
+            .collect();
+
+        *self.net_stats.lock().unwrap() = NetReport {
+            clients,
+            dropped: self.dropped,
+        };
+    }
+
+    /// Counts a message dropped for going over the endpoint's limits, banning the address for a while if it keeps flooding.
+    /// Registered clients only get the excess dropped, their address could just as well be spoofed by someone else.
+    fn rate_limited(&mut self, endpoint: Endpoint, logger: &Logger) {
+        self.dropped.rate_limited += 1;
+
+        let flooding = self
+            .limiters
+            .get_mut(&endpoint)
+            .map_or(false, |limiter| limiter.record_drop());
+        if flooding && !self.is_registered(endpoint) {
+            self.temp_ban(endpoint.addr().ip(), logger);
+        }
+    }
+
+    /// Ignores everything from an address that isn't a registered client for a while
+    fn temp_ban(&mut self, ip: IpAddr, logger: &Logger) {
+        logger.log(format!(
+            "Banned IP {ip} for {} seconds for flooding the server",
+            TEMP_BAN_DURATION.as_secs()
+        ));
+        self.temp_bans.insert(ip, Instant::now() + TEMP_BAN_DURATION);
+
+        let registered = &self.registered_clients;
+        self.limiters
+            .retain(|endpoint, _| endpoint.addr().ip() != ip || registered.contains_key(endpoint));
+    }
+
+    /// Registered clients behind a banned address keep playing, the ban is for whoever floods from it
+    fn is_temp_banned(&self, endpoint: Endpoint) -> bool {
+        !self.is_registered(endpoint)
+            && self
+                .temp_bans
+                .get(&endpoint.addr().ip())
+                .map_or(false, |until| *until > Instant::now())
+    }
+
+    /// Limiter of an endpoint, created for new ones as long as there is room.
+    /// None means the endpoint can't be tracked right now and its messages should be dropped.
+    fn limiter(&mut self, endpoint: Endpoint) -> Option<&mut RateLimiter> {
+        if !self.limiters.contains_key(&endpoint)
+            && self.limiters.len() >= MAX_LIMITERS
+            && !self.is_registered(endpoint)
+        {
+            return None;
+        }
+
+        Some(self.limiters.entry(endpoint).or_default())
+    }
+
+    /// Counts a message that didn't decode or wasn't trusted, only logging a few per endpoint
+    fn invalid_message(&mut self, endpoint: Endpoint, logger: &Logger, warning: &str) {
+        self.dropped.invalid += 1;
+
+        if let Some(limiter) = self.limiters.get_mut(&endpoint) {
+            if limiter.allow(MessageKind::Invalid) {
+                logger.log(format!("Warning: {warning} from {}", endpoint.addr()));
+            } else if limiter.record_drop() && !self.is_registered(endpoint) {
+                self.temp_ban(endpoint.addr().ip(), logger);
+            }
+        }
+    }
+
+    /// Answers a message carrying the token of a session that isn't registered to the endpoint it came from.
+    /// Sessions that are still around get no answer, so the client notices it's cut off and resumes.
+    /// Ones that are over get told so, instead of pongs that would keep the client waiting forever.
+    fn stale_session(&mut self, endpoint: Endpoint, token: SessionToken, message: &FromClientMessage, logger: &Logger) {
+        let alive = self.suspended_clients.contains_key(&token)
+            || self.registered_clients.values().any(|client| client.token == token);
+        if alive || matches!(message, FromClientMessage::Leave) {
+            return;
+        }
+
+        if let Err(err) = events::join::reject(self, endpoint, JoinRejection::SessionExpired) {
+            logger.log(format!("Warning: {err}"))
+        }
+    }
+
+    /// Suspends clients that crashed or lost their connection without leaving.
+    /// Their players are despawned once they haven't resumed within the grace period.
+    fn drop_silent_clients(&mut self) {
+        let timeout = self.config.client_timeout;
+        let silent: Vec<Endpoint> = self
+            .registered_clients
+            .iter()
+            .filter(|(_, client)| client.last_activity.elapsed() > timeout)
+            .map(|(&endpoint, _)| endpoint)
+            .collect();
+
+        let grace_period = self.config.resume_grace_period;
+        let expired: Vec<SessionToken> = self
+            .suspended_clients
+            .iter()
+            .filter(|(_, suspended)| suspended.since.elapsed() > grace_period)
+            .map(|(&token, _)| token)
+            .collect();
+
+        if silent.is_empty() && expired.is_empty() {
+            return;
+        }
+
+        let logger = self.ecs.resources.get::<Logger>().unwrap().clone();
+        for endpoint in silent {
+            logger.log(format!(
+                "Participant with ip {} timed out after {} seconds of silence",
+                endpoint.addr(),
+                timeout.as_secs()
+            ));
+            self.suspend(endpoint);
+        }
+
+        for token in expired {
+            if let Some(suspended) = self.suspended_clients.remove(&token) {
+                logger.log("Despawned a timed out participant that didn't come back");
+                self.ecs.despawn_participant(suspended.entity).ok();
+            }
+        }
+    }
+
+    /// Keeps the player of a client that lost its connection around, so the client can resume
+    fn suspend(&mut self, endpoint: Endpoint) {
+        let client = match self.registered_clients.remove(&endpoint) {
+            Some(client) => client,
+            None => return,
+        };
+
+        // Stop the player from running into a wall for the whole grace period
+        if let Ok((input, queue)) = self
+            .ecs
+            .world
+            .query_one_mut::<(&mut InputState, &mut InputQueue)>(client.entity)
+        {
+            *input = InputState::default();
+            queue.0.clear();
+        }
+
+        self.suspended_clients.insert(
+            client.token,
+            SuspendedClient {
+                entity: client.entity,
+                since: Instant::now(),
+            },
+        );
+    }
+
+    /// Resends reliable packets that clients haven't acknowledged in time.
+    /// Clients that have fallen too far behind are disconnected instead of buffering for them forever.
+    fn resend_reliable(&mut self) {
+        let mut overflowing = Vec::new();
+        for (&endpoint, client) in self.registered_clients.iter_mut() {
+            if client.reliable.is_overflowing() {
+                overflowing.push(endpoint);
+                continue;
+            }
+
+            for packet in client.reliable.resend(RESEND_TIMEOUT) {
+                send_tracked(&self.network, endpoint, packet, &mut client.stats);
+            }
+        }
+
+        let logger = self.ecs.resources.get::<Logger>().unwrap().clone();
+        for endpoint in overflowing {
+            let reason = DisconnectReason::Kicked("Too many unacknowledged messages".to_string());
+            if let Err(err) = events::disconnect::execute(self, endpoint, reason) {
+                logger.log(format!("Warning: {err}"))
+            }
+        }
+    }
+
+    /// Keeps the server listed on the master server
+    fn send_heartbeat(&mut self) {
+        self.last_heartbeat = Some(Instant::now());
+
+        let master = match self.master {
+            Some(master) => master,
+            None => return,
+        };
+
+        let heartbeat = ToMasterMessage::Heartbeat(Heartbeat {
+            name: self.config.name.clone(),
+            port: self.addr.port(),
+            players: self.player_count(),
+            max_players: self.config.max_players,
+            protocol_version: PROTOCOL_VERSION,
+            nonce: self.master_nonce,
+        });
+        let data = bincode::serialize(&heartbeat).expect("Heartbeats should always be serializable");
+        self.network.send(master, &data);
+    }
+
+    /// Answers the master server's challenge right away, so the server gets listed without waiting for the next heartbeat
+    fn master_message(&mut self, data: &[u8], logger: &Logger) {
+        match bincode::deserialize(data) {
+            Ok(FromMasterMessage::Challenge { port, nonce }) if port == self.addr.port() => {
+                self.master_nonce = Some(nonce);
+                self.send_heartbeat();
+            }
+            Ok(_) => {}
+            Err(_) => logger.log("Warning: Invalid message from the master server"),
+        }
+    }
+
     pub fn run(&mut self) {
-        self.handle_ticks();
+        if let Some(conditions) = self.config.simulate.clone() {
+            self.network.simulate(conditions);
+        }
+
         let logger = self.ecs.resources.get::<Logger>().unwrap().clone();
+        if let Some(master) = self.config.master.clone() {
+            if self.addr.ip().is_loopback() {
+                logger.log(format!(
+                    "Warning: not listing the server on master server {master}, it's only reachable from this machine on {}",
+                    self.addr
+                ));
+            } else {
+                match self.handler.network().connect(Transport::Udp, master.as_str()) {
+                    Ok((endpoint, _)) => {
+                        logger.log(format!("Listing the server on master server {master}"));
+                        self.master = Some(endpoint);
+                    }
+                    Err(err) => logger.log(format!("Warning: failed to reach master server {master}: {err}")),
+                }
+            }
+        }
+
+        self.handle_ticks();
         let listener = self.listener.take().unwrap();
 
         listener.for_each(move |event| match event {
@@ -128,31 +733,166 @@ impl Server {
                 Signal::Tick => {
                     self.handle_ticks();
                 }
+                Signal::Kick { name, reason } => {
+                    self.kick(&name, DisconnectReason::Kicked(reason), &logger);
+                }
+                Signal::Ban { name, reason } => {
+                    if let Some(endpoint) = self.find_client(&name) {
+                        self.banned.insert(endpoint.addr().ip());
+                    }
+                    self.kick(&name, DisconnectReason::Banned(reason), &logger);
+                }
+                Signal::Shutdown => {
+                    let endpoints: Vec<Endpoint> = self.registered_clients.keys().copied().collect();
+                    for endpoint in endpoints {
+                        if let Err(err) =
+                            events::disconnect::execute(self, endpoint, DisconnectReason::Shutdown)
+                        {
+                            logger.log(format!("Warning: {err}"))
+                        }
+                    }
+                    if let (Some(master), Some(nonce)) = (self.master, self.master_nonce) {
+                        let unregister = ToMasterMessage::Unregister { port: self.addr.port(), nonce };
+                        let data = bincode::serialize(&unregister)
+                            .expect("Master server messages should always be serializable");
+                        // Sent directly, a simulated delay would outlive the server
+                        self.handler.network().send(master, &data);
+                    }
+                    self.handler.stop();
+                }
             },
             NodeEvent::Network(net_event) => {
+                // Only connection based transports tell us when a client goes away
+                if let NetEvent::Disconnected(endpoint) = net_event {
+                    if self.is_registered(endpoint) {
+                        logger.log(format!("Participant with ip {} lost its connection", endpoint.addr()));
+                        self.suspend(endpoint);
+                    }
+                }
+
                 if let NetEvent::Message(endpoint, input_data) = net_event {
-                    let message: FromClientMessage = match bincode::deserialize(input_data) {
+                    if Some(endpoint) == self.master {
+                        self.master_message(input_data, &logger);
+                        return;
+                    }
+
+                    if self.is_temp_banned(endpoint) {
+                        self.dropped.banned += 1;
+                        return;
+                    }
+
+                    // Checked before decoding, so flooding doesn't cost anything more than receiving.
+                    // Garbage gets a limiter too, so it's counted and banned like any other flood.
+                    let allowed = match self.limiter(endpoint) {
+                        Some(limiter) => limiter.allow_datagram(),
+                        None => {
+                            self.dropped.rate_limited += 1;
+                            return;
+                        }
+                    };
+                    if !allowed {
+                        self.rate_limited(endpoint, &logger);
+                        return;
+                    }
+
+                    if Some(endpoint.resource_id()) == self.discovery {
+                        if let Err(err) = events::discovery::execute(self, endpoint, input_data) {
+                            logger.log(format!("Warning: {err}"))
+                        }
+                        return;
+                    }
+
+                    let ClientMessage { token, sequence, message } = match codec::decode(input_data) {
                         Ok(m) => m,
                         Err(_) => {
-                            logger.log("Warning: Invalid message sent to server");
+                            self.invalid_message(endpoint, &logger, "Invalid message sent to server");
                             return;
                         }
                     };
 
+                    // Anyone can send from a spoofed address, but only the real client knows the token
+                    if let Some(client) = self.registered_clients.get_mut(&endpoint) {
+                        if token != Some(client.token) {
+                            self.invalid_message(endpoint, &logger, "Dropped message with a wrong session token");
+                            return;
+                        }
+                        client.stats.received(input_data.len(), sequence);
+                    }
+
+                    let allowed = self
+                        .limiters
+                        .get_mut(&endpoint)
+                        .is_some_and(|limiter| limiter.allow(MessageKind::of(&message)));
+                    if !allowed {
+                        self.rate_limited(endpoint, &logger);
+                        return;
+                    }
+
+                    if let Some(token) = token.filter(|_| !self.is_registered(endpoint)) {
+                        if !matches!(message, FromClientMessage::Join(_) | FromClientMessage::Resume(_)) {
+                            self.stale_session(endpoint, token, &message, &logger);
+                            return;
+                        }
+                    }
+
                     // logger.log(format!("Event {message:?}"));
 
                     match message {
                         FromClientMessage::Ping => {
-                            events::ping::execute(&logger, &self.handler, endpoint).unwrap()
+                            self.register_activity(endpoint);
+                            if let Err(err) = events::ping::execute(self, &logger, endpoint) {
+                                logger.log(format!("Warning: {err}"))
+                            }
                         }
-                        FromClientMessage::Leave => events::leave::execute(self, endpoint).unwrap(),
-                        FromClientMessage::Join(username) => {
-                            events::join::execute(self, endpoint, &username).unwrap();
+                        FromClientMessage::Leave => {
+                            if let Err(err) = events::leave::execute(self, endpoint) {
+                                logger.log(format!("Warning: {err}"))
+                            }
                         }
-                        FromClientMessage::UpdateInputs(updated_input_state) => {
-                            if let Err(err) =
-                                events::update_inputs::execute(self, updated_input_state, endpoint)
-                            {
+                        FromClientMessage::Join(request) => {
+                            if let Err(err) = events::join::execute(self, endpoint, &request) {
+                                logger.log(format!("Warning: {err}"))
+                            }
+                        }
+                        FromClientMessage::Resume(request) => {
+                            if let Err(err) = events::resume::execute(self, endpoint, token, &request) {
+                                logger.log(format!("Warning: {err}"))
+                            }
+                        }
+                        FromClientMessage::UpdateInputs(sequence, updated_input_state, view_tick) => {
+                            self.register_activity(endpoint);
+                            if let Err(err) = events::update_inputs::execute(
+                                self,
+                                sequence,
+                                updated_input_state,
+                                view_tick,
+                                endpoint,
+                            ) {
+                                logger.log(format!("Warning: {err}"))
+                            }
+                        }
+                        FromClientMessage::Query => {
+                            if let Err(err) = events::query::execute(self, endpoint) {
+                                logger.log(format!("Warning: {err}"))
+                            }
+                        }
+                        FromClientMessage::Ack(sequence) => {
+                            if let Err(err) = events::ack::execute(self, sequence, endpoint) {
+                                logger.log(format!("Warning: {err}"))
+                            }
+                        }
+                        FromClientMessage::AckSnapshot(tick) => {
+                            if let Err(err) = events::ack_snapshot::execute(self, tick, endpoint) {
+                                logger.log(format!("Warning: {err}"))
+                            }
+                        }
+                        FromClientMessage::Chat(text) => {
+                            if let Err(err) = events::chat::execute(self, endpoint, &text) {
+                                logger.log(format!("Warning: {err}"))
+                            }
+                        }
+                        FromClientMessage::Play => {
+                            if let Err(err) = events::play::execute(self, endpoint) {
                                 logger.log(format!("Warning: {err}"))
                             }
                         }
@@ -165,4 +905,47 @@ impl Server {
     pub fn is_registered(&self, endpoint: Endpoint) -> bool {
         self.registered_clients.contains_key(&endpoint)
     }
+
+    /// Clients taking up a player slot, spectators aren't counted.
+    /// Players of suspended clients count too, their slot is kept for when they resume.
+    pub fn player_count(&self) -> usize {
+        self.participants().filter(|&entity| !self.ecs.is_spectator(entity)).count()
+    }
+
+    pub fn spectator_count(&self) -> usize {
+        self.participants().filter(|&entity| self.ecs.is_spectator(entity)).count()
+    }
+
+    /// Entities of every registered and suspended client
+    pub(crate) fn participants(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.registered_clients
+            .values()
+            .map(|client| client.entity)
+            .chain(self.suspended_clients.values().map(|client| client.entity))
+    }
+
+    fn register_activity(&mut self, endpoint: Endpoint) {
+        if let Some(client) = self.registered_clients.get_mut(&endpoint) {
+            client.last_activity = Instant::now();
+        }
+    }
+
+    /// Finds the endpoint of the client whose player or spectator has the given name
+    pub fn find_client(&self, name: &str) -> Option<Endpoint> {
+        self.registered_clients
+            .iter()
+            .find(|(_, client)| self.ecs.participant_name(client.entity).map_or(false, |participant| participant == name))
+            .map(|(&endpoint, _)| endpoint)
+    }
+
+    fn kick(&mut self, name: &str, reason: DisconnectReason, logger: &Logger) {
+        match self.find_client(name) {
+            Some(endpoint) => {
+                if let Err(err) = events::disconnect::execute(self, endpoint, reason) {
+                    logger.log(format!("Warning: {err}"))
+                }
+            }
+            None => logger.log(format!("Warning: no player named {name}")),
+        }
+    }
 }
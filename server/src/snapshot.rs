@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+
+use common::defaults::TICKS_PER_SECOND;
+use common::ecs::components::{EcsProtocol, InsertComponent, RemoveComponent};
+use common::snapshot::Snapshot;
+use common::Tick;
+
+/// How far behind the current tick a client's acknowledged snapshot can be before it gets a full snapshot instead
+pub const MAX_BASELINE_AGE: Tick = TICKS_PER_SECOND * 2;
+
+/// The last known value of a single component, `None` if it was removed
+#[derive(Debug)]
+struct Entry {
+    changed: Tick,
+    value: Option<InsertComponent>,
+}
+
+/// Keeps the latest value of every component replicated over the unreliable channel
+/// along with the tick it last changed on.
+/// This is enough to build a delta against any baseline newer than the [horizon](SnapshotStore::prune),
+/// without having to keep a copy of the whole world for every tick.
+///
+/// Spawns and despawns go over the reliable channel, so the store only needs to forget despawned entities.
+#[derive(Debug, Default)]
+pub struct SnapshotStore {
+    components: HashMap<(NonZeroU64, RemoveComponent), Entry>,
+
+    /// Baselines before this tick can no longer be used for deltas
+    horizon: Tick,
+}
+
+impl SnapshotStore {
+    /// Record [EcsProtocol] changes as having happened on the given tick.
+    /// Inserts that don't actually change the value are ignored, so they won't end up in any deltas.
+    pub fn record(&mut self, tick: Tick, changes: impl IntoIterator<Item = EcsProtocol>) {
+        for change in changes {
+            match change {
+                EcsProtocol::Insert((entity, component)) => {
+                    let key = (entity, component.to_remove());
+                    let entry = self.components.entry(key).or_insert(Entry {
+                        changed: tick,
+                        value: None,
+                    });
+
+                    if entry.value.as_ref() != Some(&component) {
+                        entry.changed = tick;
+                        entry.value = Some(component);
+                    }
+                }
+                EcsProtocol::Remove((entity, component)) => {
+                    if let Some(entry) = self.components.get_mut(&(entity, component)) {
+                        if entry.value.is_some() {
+                            entry.changed = tick;
+                            entry.value = None;
+                        }
+                    }
+                }
+                EcsProtocol::Despawn(entity) => self.forget(entity),
+            }
+        }
+    }
+
+    /// Drop everything known about a despawned entity
+    pub fn forget(&mut self, entity: NonZeroU64) {
+        self.components.retain(|(id, _), _| *id != entity);
+    }
+
+    /// Build a [Snapshot] of the given tick relative to a baseline.
+    /// Falls back to a full snapshot if there is no baseline or it's too old.
+    pub fn delta(&self, tick: Tick, baseline: Option<Tick>) -> Snapshot {
+        let baseline = baseline.filter(|&baseline| self.is_valid_baseline(baseline));
+
+        let changes = self
+            .components
+            .iter()
+            .filter(|(_, entry)| match baseline {
+                Some(baseline) => entry.changed > baseline,
+                None => entry.value.is_some(),
+            })
+            .map(|(&(entity, kind), entry)| match &entry.value {
+                Some(component) => EcsProtocol::Insert((entity, component.clone())),
+                None => EcsProtocol::Remove((entity, kind)),
+            })
+            .collect();
+
+        Snapshot {
+            tick,
+            baseline,
+            changes,
+        }
+    }
+
+    /// Forget the history needed for baselines before `horizon`.
+    /// Should be called with the oldest baseline any client still uses.
+    pub fn prune(&mut self, horizon: Tick) {
+        self.horizon = self.horizon.max(horizon);
+
+        let horizon = self.horizon;
+        self.components
+            .retain(|_, entry| entry.value.is_some() || entry.changed > horizon);
+    }
+
+    /// Whether a baseline is still usable for building deltas
+    pub fn is_valid_baseline(&self, baseline: Tick) -> bool {
+        baseline >= self.horizon
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::ecs::components::{Health, Position};
+    use glam::Vec2;
+
+    use super::*;
+
+    fn entity(id: u64) -> NonZeroU64 {
+        NonZeroU64::new(id).unwrap()
+    }
+
+    fn position(id: u64, x: f32) -> EcsProtocol {
+        EcsProtocol::Insert((entity(id), InsertComponent::Position(Position(Vec2::new(x, 0.0)))))
+    }
+
+    #[test]
+    fn delta_only_has_changes_after_the_baseline() {
+        let mut store = SnapshotStore::default();
+        store.record(1, [position(1, 1.0), position(2, 1.0)]);
+        store.record(2, [position(1, 2.0)]);
+        // Same value again, not a change
+        store.record(3, [position(2, 1.0)]);
+
+        let delta = store.delta(3, Some(1));
+        assert_eq!(delta.baseline, Some(1));
+        assert_eq!(delta.changes, vec![position(1, 2.0)]);
+
+        let full = store.delta(3, None);
+        assert!(full.is_full());
+        assert_eq!(full.changes.len(), 2);
+    }
+
+    #[test]
+    fn removals_are_sent_in_deltas_but_not_full_snapshots() {
+        let mut store = SnapshotStore::default();
+        store.record(
+            1,
+            [EcsProtocol::Insert((entity(1), InsertComponent::Health(Health(100.0))))],
+        );
+        store.record(2, [EcsProtocol::Remove((entity(1), RemoveComponent::Health))]);
+
+        assert_eq!(
+            store.delta(2, Some(1)).changes,
+            vec![EcsProtocol::Remove((entity(1), RemoveComponent::Health))]
+        );
+        assert!(store.delta(2, None).changes.is_empty());
+    }
+
+    #[test]
+    fn pruned_baselines_fall_back_to_full_snapshots() {
+        let mut store = SnapshotStore::default();
+        store.record(1, [position(1, 1.0)]);
+        store.prune(5);
+
+        assert!(!store.is_valid_baseline(4));
+        assert!(store.delta(6, Some(4)).is_full());
+        assert_eq!(store.delta(6, Some(5)).baseline, Some(5));
+
+        // The horizon never moves back
+        store.prune(2);
+        assert!(!store.is_valid_baseline(4));
+    }
+
+    #[test]
+    fn forgotten_entities_are_left_out() {
+        let mut store = SnapshotStore::default();
+        store.record(1, [position(1, 1.0), position(2, 1.0)]);
+        store.record(2, [EcsProtocol::Despawn(entity(1))]);
+
+        assert_eq!(store.delta(2, None).changes, vec![position(2, 1.0)]);
+    }
+}